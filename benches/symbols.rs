@@ -0,0 +1,90 @@
+// Allocations and time for looking names up by String, as identifiers were
+// before interning, and by Symbol. Also counts what a whole evaluation of a
+// program full of variable references allocates.
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    collections::HashMap,
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use spemath::core::runtime::run_source;
+use spemath::parser::symbol::Symbol;
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const NAMES: usize = 64;
+const LOOKUPS: usize = 200_000;
+
+fn measure(label: &str, f: impl FnOnce()) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    f();
+    let elapsed: Duration = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    println!(
+        "{:<28} {:>10} allocations {:>12.2?}",
+        label, allocations, elapsed
+    );
+}
+
+fn main() {
+    let names: Vec<String> = (0..NAMES).map(|i| format!("variable_{}", i)).collect();
+
+    // What the parser and evaluator did per reference: clone the name, hash the string
+    let by_string: HashMap<String, f64> = names.iter().map(|name| (name.clone(), 1.0)).collect();
+    measure("String keys", || {
+        let mut total = 0.0;
+        for i in 0..LOOKUPS {
+            let name = names[i % NAMES].clone();
+            total += by_string[black_box(&name)];
+        }
+        black_box(total);
+    });
+
+    // Interned once, then copied and hashed as an integer
+    let symbols: Vec<Symbol> = names.iter().map(|name| Symbol::intern(name)).collect();
+    let by_symbol: HashMap<Symbol, f64> = symbols.iter().map(|&symbol| (symbol, 1.0)).collect();
+    measure("Symbol keys", || {
+        let mut total = 0.0;
+        for i in 0..LOOKUPS {
+            let symbol = symbols[i % NAMES];
+            total += by_symbol[black_box(&symbol)];
+        }
+        black_box(total);
+    });
+
+    measure("Symbol::as_str", || {
+        let mut length = 0;
+        for i in 0..LOOKUPS {
+            length += black_box(symbols[i % NAMES]).as_str().len();
+        }
+        black_box(length);
+    });
+
+    let mut source: String = names.iter().map(|name| format!("{} = 1\n", name)).collect();
+    for _ in 0..100 {
+        source.push_str(&names.join(" + "));
+        source.push('\n');
+    }
+    measure("evaluate 6400 references", || {
+        black_box(run_source(&source).unwrap());
+    });
+}
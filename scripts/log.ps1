@@ -3,7 +3,7 @@ param(
 )
 
 $env:RUST_LOG = $logLevel
-cargo run > output.log 2>&1
+cargo run -- input.spemath > output.log 2>&1
 
 $env:RUST_LOG = $null
 
@@ -3,6 +3,6 @@ param(
 )
 
 $env:RUST_LOG = $logLevel
-cargo run
+cargo run -- input.spemath
 
 $env:RUST_LOG = $null
@@ -0,0 +1,70 @@
+use crate::{
+    interpreter::error::EvalError, lexer::error::LexerError, parser::ast::Statement,
+    parser::error::ParserError,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Lexer,
+    Parser,
+    Runtime,
+    // Not an error: evaluation went on
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub stage: Stage,
+    pub message: String,
+    pub line: usize,
+    pub col: usize,
+    pub len: usize,
+}
+
+impl From<&LexerError> for Diagnostic {
+    fn from(err: &LexerError) -> Self {
+        let (line, col) = err.location();
+        Diagnostic {
+            stage: Stage::Lexer,
+            message: err.to_string(),
+            line,
+            col,
+            len: err.span_len(),
+        }
+    }
+}
+
+impl From<&ParserError> for Diagnostic {
+    fn from(err: &ParserError) -> Self {
+        let (line, col, _) = err.location();
+        Diagnostic {
+            stage: Stage::Parser,
+            message: err.to_string(),
+            line,
+            col,
+            len: err.span_len(),
+        }
+    }
+}
+
+impl Diagnostic {
+    pub fn runtime(err: &EvalError, statement: &Statement) -> Self {
+        Diagnostic {
+            stage: Stage::Runtime,
+            message: err.to_string(),
+            line: statement.span.line,
+            col: statement.span.col,
+            len: statement.len,
+        }
+    }
+
+    pub fn warning(message: &str, statement: &Statement) -> Self {
+        Diagnostic {
+            stage: Stage::Warning,
+            message: message.to_string(),
+            line: statement.span.line,
+            col: statement.span.col,
+            len: statement.len,
+        }
+    }
+}
@@ -0,0 +1,139 @@
+// Minimal JSON encoding of results for the WASM boundary
+use crate::{
+    core::{diagnostic::Diagnostic, runtime::EvalOutcome},
+    interpreter::value::Value,
+    lexer::token::{SpannedToken, Token},
+};
+
+pub fn value_to_json(value: &Value) -> String {
+    match value {
+        Value::Number(n) if n.is_nan() => string_to_json("NaN"),
+        Value::Number(n) if n.is_infinite() => {
+            string_to_json(if *n > 0.0 { "inf" } else { "-inf" })
+        }
+        Value::Number(n) => n.to_string(),
+        Value::Integer(n) => n.to_string(),
+        Value::Rational { num, den } => (*num as f64 / *den as f64).to_string(),
+        Value::String(s) => string_to_json(s),
+        Value::Boolean(b) => b.to_string(),
+        Value::List(items) => {
+            let items: Vec<String> = items.iter().map(value_to_json).collect();
+            format!("[{}]", items.join(","))
+        }
+        Value::Function(function) => {
+            let name = match function.name {
+                Some(name) => string_to_json(name.as_str()),
+                None => "null".to_string(),
+            };
+            format!("{{\"type\":\"function\",\"name\":{}}}", name)
+        }
+        Value::Builtin(builtin) => format!(
+            "{{\"type\":\"function\",\"name\":{}}}",
+            string_to_json(builtin.name)
+        ),
+        Value::Unit => "null".to_string(),
+    }
+}
+
+pub fn outcomes_to_json(outcomes: &[EvalOutcome]) -> String {
+    if outcomes.is_empty() {
+        return "{\"values\":[],\"message\":\"no expressions\"}".to_string();
+    }
+
+    let values: Vec<String> = outcomes
+        .iter()
+        .map(|outcome| match &outcome.result {
+            Ok(value) => format!(
+                "{{\"index\":{},\"value\":{}}}",
+                outcome.index,
+                value_to_json(value)
+            ),
+            Err(err) => format!(
+                "{{\"index\":{},\"error\":{}}}",
+                outcome.index,
+                string_to_json(&err.to_string())
+            ),
+        })
+        .collect();
+    format!("{{\"values\":[{}]}}", values.join(","))
+}
+
+// One JSON object per line, tagged with the value's type so output can be styled
+pub fn outcomes_to_typed_json_lines(outcomes: &[EvalOutcome]) -> String {
+    outcomes
+        .iter()
+        .map(|outcome| match &outcome.result {
+            Ok(value) => format!(
+                "{{\"value\":{},\"type\":{}}}\n",
+                value_to_json(value),
+                string_to_json(value.type_name())
+            ),
+            Err(err) => format!(
+                "{{\"error\":{},\"type\":\"error\"}}\n",
+                string_to_json(&err.to_string())
+            ),
+        })
+        .collect()
+}
+
+pub fn errors_to_json(errors: &[String]) -> String {
+    let errors: Vec<String> = errors.iter().map(|err| string_to_json(err)).collect();
+    format!("{{\"errors\":[{}]}}", errors.join(","))
+}
+
+// Every token, whitespace and newlines included, so positions can be reconstructed
+pub fn tokens_to_json(tokens: &[SpannedToken], errors: &[Diagnostic]) -> String {
+    let tokens: Vec<String> = tokens
+        .iter()
+        .map(|token| {
+            let text = match &token.value {
+                Token::Eof => String::new(),
+                Token::Newline => "\n".to_string(),
+                other => other.to_string(),
+            };
+            format!(
+                "{{\"kind\":{},\"text\":{},\"line\":{},\"col\":{},\"pos\":{},\"len\":{}}}",
+                string_to_json(token.value.kind()),
+                string_to_json(&text),
+                token.span.line,
+                token.span.col,
+                token.span.pos,
+                token.span.len
+            )
+        })
+        .collect();
+    let errors: Vec<String> = errors
+        .iter()
+        .map(|err| {
+            format!(
+                "{{\"message\":{},\"line\":{},\"col\":{},\"len\":{}}}",
+                string_to_json(&err.message),
+                err.line,
+                err.col,
+                err.len
+            )
+        })
+        .collect();
+    format!(
+        "{{\"tokens\":[{}],\"errors\":[{}]}}",
+        tokens.join(","),
+        errors.join(",")
+    )
+}
+
+pub fn string_to_json(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -1 +1,4 @@
-pub mod runtime;
\ No newline at end of file
+pub mod diagnostic;
+pub mod json;
+pub mod runtime;
+pub mod tests;
\ No newline at end of file
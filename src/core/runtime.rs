@@ -1,38 +1,325 @@
+use crate::core::diagnostic::{Diagnostic, Stage};
+use crate::lexer::token::SpannedToken;
 use crate::lexer::tokenizer::Lexer;
+use crate::parser::ast::Expr;
+use crate::parser::brackets::check_brackets;
 use crate::parser::pratt::Parser;
-use crate::interpreter::eval::Evaluator;
+use crate::parser::symbol::Symbol;
+use crate::interpreter::error::EvalError;
+use crate::interpreter::eval::{AngleMode, Evaluator};
 use crate::interpreter::value::Value;
 
-pub fn run_source(source: &str) -> Result<String, String> {
+fn parse_source(source: &str) -> Result<Vec<Expr>, Vec<String>> {
     // 1. LEXER
     let mut lexer = Lexer::new(source);
-    let tokens = lexer.tokenize().map_err(|errs| {
-        errs.into_iter()
-            .map(|e| e.to_string())
-            .collect::<Vec<_>>()
-            .join("\n")
-    })?;
+    let tokens = lexer
+        .tokenize()
+        .map_err(|errs| errs.iter().map(|e| e.to_string()).collect::<Vec<_>>())?;
 
     // 2. PARSER
     let mut parser = Parser::new(tokens);
-    let exprs = parser.parse().map_err(|errs| {
-        errs.into_iter()
-            .map(|e| e.to_string())
-            .collect::<Vec<_>>()
-            .join("\n")
+    parser
+        .parse()
+        .map_err(|errs| errs.iter().map(|e| e.to_string()).collect())
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    pub explain_precedence: bool,
+    // Print the value of top-level assignments like any other expression
+    pub echo_assignments: bool,
+    // Bind `_` to the latest value, as an interactive session does
+    pub bind_last_result: bool,
+    // Warn when a definition uses a name that is not defined yet. Only
+    // `run_source_detailed_with_options` reports the warnings.
+    pub check_definitions: bool,
+}
+
+fn is_echoed(expr: &Expr, value: &Value, options: &RunOptions) -> bool {
+    match (expr, value) {
+        (_, Value::Unit) => false,
+        (Expr::Assignment { .. }, _) => options.echo_assignments,
+        _ => true,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EvalOutcome {
+    // Position of the expression among all expressions in the source
+    pub index: usize,
+    pub result: Result<Value, EvalError>,
+}
+
+// Runs on the caller's stack. Recursion stops after DEFAULT_MAX_CALL_DEPTH calls,
+// which fits an 8 MiB stack for simple function bodies; deeply nested bodies need
+// a bigger one, as the command-line tool uses.
+pub fn run_source(source: &str) -> Result<String, String> {
+    let outcomes = run_source_structured(source).map_err(|errs| errs.join("\n"))?;
+
+    let mut output = String::new();
+    for outcome in outcomes {
+        match outcome.result {
+            Ok(value) => output.push_str(&format!("{}\n", value)),
+            Err(err) => output.push_str(&format!("Runtime Error: {}\n", err)),
+        }
+    }
+    Ok(output)
+}
+
+// One outcome per expression, leaving out the ones that would not be printed
+pub fn run_source_structured(source: &str) -> Result<Vec<EvalOutcome>, Vec<String>> {
+    let exprs = parse_source(source)?;
+    let options = RunOptions::default();
+    let mut evaluator = Evaluator::new();
+
+    Ok(exprs
+        .iter()
+        .enumerate()
+        .map(|(index, expr)| {
+            let result = evaluator.eval(expr);
+            (expr, EvalOutcome { index, result })
+        })
+        .filter(|(expr, outcome)| match &outcome.result {
+            Ok(value) => is_echoed(expr, value, &options),
+            Err(_) => true,
+        })
+        .map(|(_, outcome)| outcome)
+        .collect())
+}
+
+pub fn run_source_with_options(source: &str, options: &RunOptions) -> Result<String, String> {
+    collect_output(source, options, &mut Evaluator::new())
+}
+
+pub fn run_source_streaming(source: &str, callback: impl FnMut(&str)) -> Result<(), String> {
+    stream_source(source, &RunOptions::default(), &mut Evaluator::new(), callback)
+}
+
+// Keeps one evaluator alive so definitions carry over between runs
+pub struct Session {
+    evaluator: Evaluator,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Session {
+            evaluator: Evaluator::new(),
+        }
+    }
+
+    pub fn run(&mut self, source: &str) -> Result<String, String> {
+        let options = RunOptions {
+            bind_last_result: true,
+            ..RunOptions::default()
+        };
+        collect_output(source, &options, &mut self.evaluator)
+    }
+
+    pub fn set_angle_mode(&mut self, mode: AngleMode) {
+        self.evaluator.angle_mode = mode;
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn collect_output(
+    source: &str,
+    options: &RunOptions,
+    evaluator: &mut Evaluator,
+) -> Result<String, String> {
+    let mut output = String::new();
+    stream_source(source, options, evaluator, |line| {
+        output.push_str(line);
+        output.push('\n');
     })?;
+    Ok(output)
+}
+
+// Nothing is evaluated unless the whole source lexes and parses
+fn stream_source(
+    source: &str,
+    options: &RunOptions,
+    evaluator: &mut Evaluator,
+    mut callback: impl FnMut(&str),
+) -> Result<(), String> {
+    let exprs = parse_source(source).map_err(|errs| errs.join("\n"))?;
 
     // 3. EVALUATOR
+    for expr in exprs {
+        if options.explain_precedence && has_implied_grouping(&expr) {
+            callback(&format!("Grouping: {}", expr));
+        }
+
+        let result = evaluator.eval(&expr);
+        if options.bind_last_result
+            && let Ok(value) = &result
+            && *value != Value::Unit
+        {
+            evaluator.env.set(Symbol::intern("_"), value.clone());
+        }
+
+        match result {
+            Ok(value) if !is_echoed(&expr, &value, options) => {}
+            Ok(value) => callback(&value.to_string()),
+            Err(err) => callback(&format!("Runtime Error: {}", err)),
+        }
+    }
+
+    Ok(())
+}
+
+// The syntax tree of every expression as a JSON array, in the derived serde format
+#[cfg(feature = "serde")]
+pub fn parse_to_json(source: &str) -> Result<String, String> {
+    let exprs = parse_source(source).map_err(|errs| errs.join("\n"))?;
+    serde_json::to_string(&exprs).map_err(|err| err.to_string())
+}
+
+// Lexes as far as possible, skipping over invalid input
+pub fn tokenize_source(source: &str) -> (Vec<SpannedToken>, Vec<Diagnostic>) {
+    let (tokens, errors) = Lexer::new(source).tokenize_recovering();
+    (tokens, errors.iter().map(Diagnostic::from).collect())
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CheckOptions {
+    // Match brackets first and, on a mismatch, report only that instead of parsing
+    pub check_brackets: bool,
+}
+
+// Lexes and parses without evaluating, reporting every error found
+pub fn check_source(source: &str) -> Result<(), Vec<Diagnostic>> {
+    check_source_with_options(source, &CheckOptions::default())
+}
+
+pub fn check_source_with_options(
+    source: &str,
+    options: &CheckOptions,
+) -> Result<(), Vec<Diagnostic>> {
+    let (tokens, lexer_errors) = Lexer::new(source).tokenize_recovering();
+    let mut diagnostics: Vec<Diagnostic> = lexer_errors.iter().map(Diagnostic::from).collect();
+
+    if options.check_brackets
+        && let Err(err) = check_brackets(&tokens)
+    {
+        diagnostics.push(Diagnostic::from(&err));
+    } else if let Err(parser_errors) = Parser::new(tokens).parse_statements() {
+        // A parser error on a line with a lexer error is usually caused by the skipped input
+        let lexer_lines: Vec<usize> = diagnostics.iter().map(|d| d.line).collect();
+        diagnostics.extend(
+            parser_errors
+                .iter()
+                .map(Diagnostic::from)
+                .filter(|d| !lexer_lines.contains(&d.line)),
+        );
+    }
+
+    if diagnostics.is_empty() {
+        Ok(())
+    } else {
+        Err(diagnostics)
+    }
+}
+
+pub fn run_source_detailed(source: &str) -> Result<String, Vec<Diagnostic>> {
+    run_source_detailed_with_options(source, &RunOptions::default()).map(|(output, _)| output)
+}
+
+// The output along with any warnings, or the errors and warnings if there are errors
+pub fn run_source_detailed_with_options(
+    source: &str,
+    options: &RunOptions,
+) -> Result<(String, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let mut lexer = Lexer::new(source);
+    let tokens = lexer
+        .tokenize()
+        .map_err(|errs| errs.iter().map(Diagnostic::from).collect::<Vec<_>>())?;
+
+    let mut parser = Parser::new(tokens);
+    let statements = parser
+        .parse_statements()
+        .map_err(|errs| errs.iter().map(Diagnostic::from).collect::<Vec<_>>())?;
+
     let mut evaluator = Evaluator::new();
+    evaluator.check_definitions = options.check_definitions;
     let mut output = String::new();
+    let mut diagnostics = Vec::new();
 
-    for expr in exprs {
-        match evaluator.eval(&expr) {
-            Ok(Value::Unit) => {}
-            Ok(value) => output.push_str(&format!("{:?}\n", value)),
-            Err(err) => output.push_str(&format!("Runtime Error: {}\n", err)),
+    for statement in &statements {
+        let result = evaluator.eval(&statement.expr);
+        for warning in evaluator.warnings.drain(..) {
+            diagnostics.push(Diagnostic::warning(&warning, statement));
+        }
+        match result {
+            Ok(value) if !is_echoed(&statement.expr, &value, options) => {}
+            Ok(value) => output.push_str(&format!("{}\n", value)),
+            Err(err) => diagnostics.push(Diagnostic::runtime(&err, statement)),
         }
     }
 
-    Ok(output)
+    if diagnostics.iter().all(|d| d.stage == Stage::Warning) {
+        Ok((output, diagnostics))
+    } else {
+        Err(diagnostics)
+    }
+}
+
+// True when operator precedence decided how two operators nest
+fn has_implied_grouping(expr: &Expr) -> bool {
+    match expr {
+        Expr::Binary { left, right, .. } => {
+            matches!(**left, Expr::Binary { .. })
+                || matches!(**right, Expr::Binary { .. })
+                || has_implied_grouping(left)
+                || has_implied_grouping(right)
+        }
+        Expr::Unary { expr, .. } | Expr::Postfix { expr, .. } => has_implied_grouping(expr),
+        Expr::Assignment { value, .. }
+        | Expr::Constant { value, .. }
+        | Expr::DefaultAssignment { value, .. } => has_implied_grouping(value),
+        Expr::Function { body, .. } | Expr::Lambda { body, .. } => has_implied_grouping(body),
+        Expr::If {
+            cond,
+            then_branch,
+            else_branch,
+        } => {
+            has_implied_grouping(cond)
+                || has_implied_grouping(then_branch)
+                || has_implied_grouping(else_branch)
+        }
+        Expr::Call { args, .. } => args.iter().any(has_implied_grouping),
+        Expr::Index { target, index } => {
+            has_implied_grouping(target) || has_implied_grouping(index)
+        }
+        Expr::Range { start, end } => has_implied_grouping(start) || has_implied_grouping(end),
+        Expr::List(items) | Expr::Block(items) => items.iter().any(has_implied_grouping),
+        Expr::Number(_)
+        | Expr::Integer(_)
+        | Expr::String(_)
+        | Expr::Boolean(_)
+        | Expr::Identifier(_) => false,
+    }
+}
+
+pub fn run_source_number(source: &str) -> Result<f64, String> {
+    let exprs = parse_source(source).map_err(|errs| errs.join("\n"))?;
+    if exprs.is_empty() {
+        return Err("No expressions to evaluate".to_string());
+    }
+
+    let mut evaluator = Evaluator::new();
+    let mut last = Value::Unit;
+
+    for expr in exprs {
+        last = evaluator.eval(&expr).map_err(|err| err.to_string())?;
+    }
+
+    match last.into_float() {
+        Value::Number(n) => Ok(n),
+        other => Err(format!("Expected a number result, found {}", other.type_name())),
+    }
 }
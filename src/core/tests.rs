@@ -0,0 +1,526 @@
+#[cfg(test)]
+mod core_tests {
+    use crate::interpreter::{error::EvalError, eval::AngleMode, value::Value};
+
+    use crate::core::{
+        diagnostic::{Diagnostic, Stage},
+        json::{
+            errors_to_json, outcomes_to_json, outcomes_to_typed_json_lines, tokens_to_json,
+            value_to_json,
+        },
+        runtime::{
+            CheckOptions, RunOptions, Session, check_source, check_source_with_options, run_source,
+            run_source_detailed, run_source_detailed_with_options, run_source_number,
+            run_source_streaming, run_source_structured, run_source_with_options, tokenize_source,
+        },
+    };
+
+    #[test]
+    fn test_run_source_number() {
+        assert_eq!(run_source_number("3*4"), Ok(12.0));
+        assert_eq!(run_source_number("x = 2\nx^3"), Ok(8.0));
+    }
+
+    #[test]
+    fn test_run_source_number_non_numeric() {
+        let err = run_source_number("f(x) = x\nf").unwrap_err();
+        assert_eq!(err, "Expected a number result, found function");
+
+        assert!(run_source_number("const k = 1").is_err());
+        assert!(run_source_number("y + 1").is_err());
+    }
+
+    #[test]
+    fn test_explain_precedence() {
+        let options = RunOptions {
+            explain_precedence: true,
+            ..RunOptions::default()
+        };
+        let output = run_source_with_options("2 + 3 * 4", &options).unwrap();
+        assert!(output.starts_with("Grouping: 2 + (3 * 4)\n"));
+
+        let output = run_source_with_options("(2 + 3) * 4", &options).unwrap();
+        assert!(output.starts_with("Grouping: (2 + 3) * 4\n"));
+
+        let output = run_source_with_options("x = 2 + 3", &options).unwrap();
+        assert!(!output.contains("Grouping"));
+
+        let output = run_source_with_options("1 + 2 not in [3]", &options).unwrap();
+        assert!(
+            output.starts_with("Grouping: !((1 + 2) in [3])\n"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_echo_assignments() {
+        assert_eq!(run_source("x = 5\nx + 1"), Ok("6\n".to_string()));
+
+        let options = RunOptions {
+            echo_assignments: true,
+            ..RunOptions::default()
+        };
+        let output = run_source_with_options("x = 5\na = b = x + 1\nf(y) = y", &options);
+        assert_eq!(output, Ok("5\n6\n".to_string()));
+    }
+
+    #[test]
+    fn test_run_source_streaming() {
+        let mut lines = Vec::new();
+        run_source_streaming("1 + 1\nx = 2\nx * 3\ny", |line| {
+            lines.push(line.to_string())
+        })
+        .unwrap();
+
+        assert_eq!(
+            lines,
+            vec!["2", "6", "Runtime Error: Unknown variable: 'y'"]
+        );
+    }
+
+    #[test]
+    fn test_run_source_streaming_parse_error() {
+        let mut lines = Vec::new();
+        let result = run_source_streaming("1 +", |line| lines.push(line.to_string()));
+
+        assert!(result.is_err());
+        assert!(lines.is_empty());
+    }
+
+    #[test]
+    fn test_run_source_detailed_ok() {
+        assert_eq!(run_source_detailed("x = 2\nx * 3"), Ok("6\n".to_string()));
+    }
+
+    #[test]
+    fn test_run_source_detailed_lexer_error() {
+        let diagnostics = run_source_detailed("x = 1\ny = 12.3.4").unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        let Diagnostic {
+            stage,
+            line,
+            col,
+            len,
+            ..
+        } = &diagnostics[0];
+        assert_eq!((*stage, *line, *col, *len), (Stage::Lexer, 2, 5, 6));
+    }
+
+    #[test]
+    fn test_run_source_detailed_parser_error() {
+        let diagnostics = run_source_detailed("1 +\n(2 + 3))").unwrap_err();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].stage, Stage::Parser);
+        assert_eq!((diagnostics[0].line, diagnostics[0].col), (1, 4));
+        assert_eq!(diagnostics[1].stage, Stage::Parser);
+        assert_eq!((diagnostics[1].line, diagnostics[1].col), (2, 8));
+        assert_eq!(diagnostics[1].len, 1);
+    }
+
+    #[test]
+    fn test_run_source_detailed_runtime_error() {
+        let diagnostics = run_source_detailed("x = 1\n  x + unknown  \n2").unwrap_err();
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic {
+                stage: Stage::Runtime,
+                message: "Unknown variable: 'unknown'".to_string(),
+                line: 2,
+                col: 3,
+                len: 11,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_run_source_detailed_warnings() {
+        let source = "make() = (g(x) = x + later; g)\nh = make()\nlater = 1\nh(2)";
+        // Definitions are only checked on request
+        assert_eq!(run_source_detailed(source), Ok("3\n".to_string()));
+
+        let options = RunOptions {
+            check_definitions: true,
+            ..RunOptions::default()
+        };
+        let (output, warnings) = run_source_detailed_with_options(source, &options).unwrap();
+        assert_eq!(output, "3\n");
+        // The second one is raised while `make` runs, by the definition in its body
+        assert_eq!(
+            warnings,
+            vec![
+                Diagnostic {
+                    stage: Stage::Warning,
+                    message: "'make' uses 'later', which is not defined yet".to_string(),
+                    line: 1,
+                    col: 1,
+                    len: 30,
+                },
+                Diagnostic {
+                    stage: Stage::Warning,
+                    message: "'g' uses 'later', which is not defined yet".to_string(),
+                    line: 2,
+                    col: 1,
+                    len: 10,
+                },
+            ]
+        );
+
+        let diagnostics = run_source_detailed_with_options("f(x) = y\nf(1)", &options).unwrap_err();
+        assert_eq!(
+            diagnostics,
+            vec![
+                Diagnostic {
+                    stage: Stage::Warning,
+                    message: "'f' uses 'y', which is not defined yet".to_string(),
+                    line: 1,
+                    col: 1,
+                    len: 8,
+                },
+                Diagnostic {
+                    stage: Stage::Runtime,
+                    message: "Unknown variable: 'y'".to_string(),
+                    line: 2,
+                    col: 1,
+                    len: 4,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_run_source_comparison_program() {
+        assert_eq!(run_source("3 > 2"), Ok("true\n".to_string()));
+        assert_eq!(run_source("2 >= 3"), Ok("false\n".to_string()));
+        assert_eq!(run_source("x = 4\nx == 2 * 2"), Ok("true\n".to_string()));
+    }
+
+    #[test]
+    fn test_session_keeps_definitions() {
+        let mut session = Session::new();
+        assert_eq!(session.run("x = 5\nf(y) = y * x"), Ok(String::new()));
+        assert_eq!(session.run("x + 1"), Ok("6\n".to_string()));
+        assert_eq!(session.run("f(2)"), Ok("10\n".to_string()));
+    }
+
+    #[test]
+    fn test_session_angle_mode() {
+        let mut session = Session::new();
+        assert_eq!(session.run("anglemode()"), Ok("rad\n".to_string()));
+
+        session.set_angle_mode(AngleMode::Degrees);
+        assert_eq!(
+            session.run("anglemode()\ncos(180)"),
+            Ok("deg\n-1\n".to_string())
+        );
+
+        // Switching from source lasts for the rest of the session
+        session.run("anglemode(\"rad\")").unwrap();
+        assert_eq!(session.run("cos(0)"), Ok("1\n".to_string()));
+    }
+
+    #[test]
+    fn test_session_survives_errors() {
+        let mut session = Session::new();
+        session.run("x = 5").unwrap();
+
+        assert!(session.run("x = 12.3.4").is_err());
+        assert!(session.run("x = (1").is_err());
+        assert_eq!(
+            session.run("y"),
+            Ok("Runtime Error: Unknown variable: 'y'\n".to_string())
+        );
+        assert_eq!(session.run("x"), Ok("5\n".to_string()));
+    }
+
+    #[test]
+    fn test_check_source_clean() {
+        assert_eq!(
+            check_source("x = 1\nf(y) = y + x\nf(2) / undefined"),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_check_source_lexer_errors() {
+        let diagnostics = check_source("x = 12.3.4\ny = 2 $ 3\nz = 1").unwrap_err();
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics.iter().all(|d| d.stage == Stage::Lexer));
+        assert_eq!(diagnostics[0].line, 1);
+        assert_eq!(diagnostics[1].line, 2);
+    }
+
+    #[test]
+    fn test_check_source_collects_lexer_and_parser_errors() {
+        let diagnostics = check_source("x = 1 +\ny = 3 $\nz = * 4").unwrap_err();
+        let stages: Vec<(Stage, usize)> = diagnostics.iter().map(|d| (d.stage, d.line)).collect();
+        assert_eq!(
+            stages,
+            vec![(Stage::Lexer, 2), (Stage::Parser, 1), (Stage::Parser, 3)]
+        );
+    }
+
+    #[test]
+    fn test_check_source_brackets_first() {
+        let options = CheckOptions {
+            check_brackets: true,
+        };
+        let source = "x = (1 + 2\ny = 3\nz = [4, 5]";
+
+        // Without the pre-pass the parser only notices on the next line
+        let diagnostics = check_source(source).unwrap_err();
+        assert_eq!((diagnostics[0].line, diagnostics[0].col), (2, 1));
+
+        let diagnostics = check_source_with_options(source, &options).unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!((diagnostics[0].line, diagnostics[0].col), (1, 5));
+        assert_eq!(diagnostics[0].message, "line 1, col 5: '(' is never closed");
+
+        assert_eq!(
+            check_source_with_options("f(x) = {x; [1]}", &options),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_run_source_structured() {
+        let outcomes = run_source_structured("x = 2\nx * 3\ny\nf(a) = a\nf(x)").unwrap();
+        assert_eq!(outcomes.len(), 3);
+
+        assert_eq!(outcomes[0].index, 1);
+        assert_eq!(outcomes[0].result.as_ref().ok(), Some(&Value::Integer(6)));
+
+        assert_eq!(outcomes[1].index, 2);
+        assert!(matches!(
+            outcomes[1].result,
+            Err(EvalError::UnknownVariable(..))
+        ));
+
+        assert_eq!(outcomes[2].index, 4);
+        assert_eq!(outcomes[2].result.as_ref().ok(), Some(&Value::Integer(2)));
+    }
+
+    #[test]
+    fn test_run_source_structured_parse_errors() {
+        let errors = run_source_structured("1 +\n2 $ 3").unwrap_err();
+        assert_eq!(errors.len(), 1);
+
+        let errors = run_source_structured("1 +\n(2").unwrap_err();
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn test_programs_without_expressions() {
+        for source in [
+            "",
+            "  \n\n",
+            "// just a note",
+            "/* a\n block */\n// and a line\n",
+        ] {
+            assert_eq!(run_source(source), Ok(String::new()));
+            assert_eq!(run_source_detailed(source), Ok(String::new()));
+            assert!(run_source_structured(source).unwrap().is_empty());
+            assert_eq!(check_source(source), Ok(()));
+            assert_eq!(
+                run_source_number(source),
+                Err("No expressions to evaluate".to_string())
+            );
+        }
+    }
+
+    fn json_of(source: &str) -> String {
+        outcomes_to_json(&run_source_structured(source).unwrap())
+    }
+
+    #[test]
+    fn test_value_to_json_scalars() {
+        assert_eq!(value_to_json(&Value::Number(2.0)), "2");
+        assert_eq!(value_to_json(&Value::Number(-0.5)), "-0.5");
+        assert_eq!(value_to_json(&Value::Number(f64::NAN)), "\"NaN\"");
+        assert_eq!(value_to_json(&Value::Number(f64::INFINITY)), "\"inf\"");
+        assert_eq!(value_to_json(&Value::Number(f64::NEG_INFINITY)), "\"-inf\"");
+        assert_eq!(value_to_json(&Value::Boolean(true)), "true");
+        assert_eq!(
+            value_to_json(&Value::String("say \"hi\"\n".to_string())),
+            "\"say \\\"hi\\\"\\n\""
+        );
+        assert_eq!(value_to_json(&Value::Unit), "null");
+    }
+
+    #[test]
+    fn test_value_to_json_lists_and_functions() {
+        let nested = Value::List(vec![
+            Value::Number(1.0),
+            Value::List(vec![Value::Boolean(false), Value::String("a".to_string())]),
+            Value::List(vec![]),
+        ]);
+        assert_eq!(value_to_json(&nested), "[1,[false,\"a\"],[]]");
+
+        assert_eq!(
+            json_of("f(x) = x\nf\nsin\nx -> x"),
+            concat!(
+                "{\"values\":[",
+                "{\"index\":1,\"value\":{\"type\":\"function\",\"name\":\"f\"}},",
+                "{\"index\":2,\"value\":{\"type\":\"function\",\"name\":\"sin\"}},",
+                "{\"index\":3,\"value\":{\"type\":\"function\",\"name\":null}}",
+                "]}"
+            )
+        );
+    }
+
+    #[test]
+    fn test_outcomes_to_json() {
+        assert_eq!(
+            json_of("x = 2\nx * 3\ny"),
+            concat!(
+                "{\"values\":[{\"index\":1,\"value\":6},",
+                "{\"index\":2,\"error\":\"Unknown variable: 'y'\"}]}"
+            )
+        );
+        assert_eq!(
+            json_of("// just a note"),
+            "{\"values\":[],\"message\":\"no expressions\"}"
+        );
+        assert_eq!(
+            errors_to_json(&run_source_structured("1 +").unwrap_err()),
+            "{\"errors\":[\"line 1, col 4: Unexpected token 'end of input'\"]}"
+        );
+    }
+
+    #[test]
+    fn test_outcomes_to_typed_json_lines() {
+        let outcomes = run_source_structured("x = 3\nx * 2\nx > 1\n[1, \"a\"]\n1/3\ny").unwrap();
+        assert_eq!(
+            outcomes_to_typed_json_lines(&outcomes),
+            concat!(
+                "{\"value\":6,\"type\":\"number\"}\n",
+                "{\"value\":true,\"type\":\"boolean\"}\n",
+                "{\"value\":[1,\"a\"],\"type\":\"list\"}\n",
+                "{\"value\":0.3333333333333333,\"type\":\"number\"}\n",
+                "{\"error\":\"Unknown variable: 'y'\",\"type\":\"error\"}\n",
+            )
+        );
+        assert_eq!(outcomes_to_typed_json_lines(&[]), "");
+    }
+
+    fn tokens_json_of(source: &str) -> String {
+        let (tokens, errors) = tokenize_source(source);
+        tokens_to_json(&tokens, &errors)
+    }
+
+    #[test]
+    fn test_tokens_to_json() {
+        assert_eq!(
+            tokens_json_of("x = 1\n\"a\""),
+            concat!(
+                "{\"tokens\":[",
+                "{\"kind\":\"Identifier\",\"text\":\"x\"",
+                ",\"line\":1,\"col\":1,\"pos\":0,\"len\":1},",
+                "{\"kind\":\"Whitespace\",\"text\":\" \"",
+                ",\"line\":1,\"col\":2,\"pos\":1,\"len\":1},",
+                "{\"kind\":\"Equal\",\"text\":\"=\",\"line\":1,\"col\":3,\"pos\":2,\"len\":1},",
+                "{\"kind\":\"Whitespace\",\"text\":\" \"",
+                ",\"line\":1,\"col\":4,\"pos\":3,\"len\":1},",
+                "{\"kind\":\"Integer\",\"text\":\"1\",\"line\":1,\"col\":5,\"pos\":4,\"len\":1},",
+                "{\"kind\":\"Newline\",\"text\":\"\\n\",\"line\":1,\"col\":6,\"pos\":5,\"len\":1},",
+                "{\"kind\":\"String\",\"text\":\"\\\"a\\\"\"",
+                ",\"line\":2,\"col\":1,\"pos\":6,\"len\":3},",
+                "{\"kind\":\"Eof\",\"text\":\"\",\"line\":2,\"col\":4,\"pos\":9,\"len\":0}",
+                "],\"errors\":[]}"
+            )
+        );
+    }
+
+    #[test]
+    fn test_tokens_to_json_reports_lexer_errors() {
+        let json = tokens_json_of("1 $ 2");
+        assert!(json.contains(
+            "{\"kind\":\"Integer\",\"text\":\"2\",\"line\":1,\"col\":5,\"pos\":4,\"len\":1}"
+        ));
+        assert!(json.ends_with(concat!(
+            "\"errors\":[{\"message\":\"Unexpected character '$' at line 1, column 3\",",
+            "\"line\":1,\"col\":3,\"len\":1}]}"
+        )));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_parse_to_json() {
+        use crate::core::runtime::parse_to_json;
+
+        let json = parse_to_json("f(x) = x + 1").unwrap();
+        assert!(json.contains("{\"Function\":"));
+        assert_eq!(
+            json,
+            concat!(
+                "[{\"Function\":{\"name\":\"f\",\"args\":[\"x\"],\"body\":",
+                "{\"Binary\":{\"left\":{\"Identifier\":\"x\"},\"op\":\"Plus\",",
+                "\"right\":{\"Integer\":1}}}}}]"
+            )
+        );
+
+        assert_eq!(
+            parse_to_json("xs = [true, \"a\"]; -xs[0]").unwrap(),
+            concat!(
+                "[{\"Assignment\":{\"target\":\"xs\",\"value\":",
+                "{\"List\":[{\"Boolean\":true},{\"String\":\"a\"}]}}},",
+                "{\"Unary\":{\"op\":\"Minus\",\"expr\":",
+                "{\"Index\":{\"target\":{\"Identifier\":\"xs\"},\"index\":{\"Integer\":0}}}}}]"
+            )
+        );
+        assert_eq!(parse_to_json("").unwrap(), "[]");
+        assert!(parse_to_json("1 +").is_err());
+    }
+
+    #[test]
+    fn test_session_binds_last_result() {
+        let mut session = Session::new();
+        assert!(session.run("_").unwrap().starts_with("Runtime Error"));
+
+        session.run("2 + 3").unwrap();
+        assert_eq!(session.run("_ * 2"), Ok("10\n".to_string()));
+        assert_eq!(session.run("_ + 1"), Ok("11\n".to_string()));
+
+        // Assignments produce a value too; definitions and errors leave `_` alone
+        session.run("x = 7").unwrap();
+        session.run("f(y) = y").unwrap();
+        session.run("1 / 0").unwrap();
+        assert_eq!(session.run("_"), Ok("7\n".to_string()));
+    }
+
+    #[test]
+    fn test_session_clear() {
+        let mut session = Session::new();
+        session
+            .run("x = 5\nf(y) = y * 2\npi = 3\nconst k = 1")
+            .unwrap();
+        assert_eq!(session.run("clear()"), Ok(String::new()));
+
+        assert!(session.run("x").unwrap().starts_with("Runtime Error"));
+        assert!(session.run("f(1)").unwrap().starts_with("Runtime Error"));
+        assert_eq!(session.run("pi"), Ok(format!("{}\n", std::f64::consts::PI)));
+        // Constants are forgotten too, so the name can be assigned again
+        assert_eq!(session.run("k = 2\nk + 1"), Ok("3\n".to_string()));
+    }
+
+    #[test]
+    fn test_underscore_parameter_hides_last_result() {
+        let mut session = Session::new();
+        session.run("100").unwrap();
+        assert_eq!(
+            session.run("f(_, y) = _ + y\nf(1, 2)"),
+            Ok("3\n".to_string())
+        );
+        assert_eq!(session.run("g(y) = _ + y\ng(1)"), Ok("4\n".to_string()));
+    }
+
+    #[test]
+    fn test_run_source_does_not_bind_last_result() {
+        let output = run_source("1\n_").unwrap();
+        assert!(
+            output.ends_with("Runtime Error: Unknown variable: '_'\n"),
+            "{}",
+            output
+        );
+    }
+}
@@ -0,0 +1,861 @@
+use crate::interpreter::{
+    error::EvalError,
+    eval::{AngleMode, Evaluator},
+    value::Value,
+};
+use crate::lexer::token::Token;
+
+pub type NativeFn = fn(&mut Evaluator, Vec<Value>) -> Result<Value, EvalError>;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Builtin {
+    pub name: &'static str,
+    pub func: NativeFn,
+}
+
+impl PartialEq for Builtin {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+const BUILTINS: &[Builtin] = &[
+    Builtin {
+        name: "count",
+        func: count,
+    },
+    Builtin {
+        name: "unique",
+        func: unique,
+    },
+    Builtin {
+        name: "sort",
+        func: sort,
+    },
+    Builtin {
+        name: "keys",
+        func: keys,
+    },
+    Builtin {
+        name: "values",
+        func: values,
+    },
+    Builtin {
+        name: "min",
+        func: min,
+    },
+    Builtin {
+        name: "max",
+        func: max,
+    },
+    Builtin {
+        name: "argmin",
+        func: argmin,
+    },
+    Builtin {
+        name: "argmax",
+        func: argmax,
+    },
+    Builtin {
+        name: "max_by",
+        func: max_by,
+    },
+    Builtin {
+        name: "min_by",
+        func: min_by,
+    },
+    Builtin {
+        name: "try_map",
+        func: try_map,
+    },
+    Builtin {
+        name: "reverse",
+        func: reverse,
+    },
+    Builtin {
+        name: "flatten",
+        func: flatten,
+    },
+    Builtin {
+        name: "sum",
+        func: sum,
+    },
+    Builtin {
+        name: "product",
+        func: product,
+    },
+    Builtin {
+        name: "clamp",
+        func: clamp,
+    },
+    Builtin {
+        name: "format",
+        func: format,
+    },
+    Builtin {
+        name: "assert_eq",
+        func: assert_eq,
+    },
+    Builtin {
+        name: "nan",
+        func: nan,
+    },
+    Builtin {
+        name: "sin",
+        func: sin,
+    },
+    Builtin {
+        name: "cos",
+        func: cos,
+    },
+    Builtin {
+        name: "tan",
+        func: tan,
+    },
+    Builtin {
+        name: "sind",
+        func: sind,
+    },
+    Builtin {
+        name: "cosd",
+        func: cosd,
+    },
+    Builtin {
+        name: "tand",
+        func: tand,
+    },
+    Builtin {
+        name: "asin",
+        func: asin,
+    },
+    Builtin {
+        name: "acos",
+        func: acos,
+    },
+    Builtin {
+        name: "atan",
+        func: atan,
+    },
+    Builtin {
+        name: "sqrt",
+        func: sqrt,
+    },
+    Builtin {
+        name: "abs",
+        func: abs,
+    },
+    Builtin {
+        name: "ln",
+        func: ln,
+    },
+    Builtin {
+        name: "log10",
+        func: log10,
+    },
+    Builtin {
+        name: "exp",
+        func: exp,
+    },
+    Builtin {
+        name: "floor",
+        func: floor,
+    },
+    Builtin {
+        name: "ceil",
+        func: ceil,
+    },
+    Builtin {
+        name: "round",
+        func: round,
+    },
+    Builtin {
+        name: "exactdiv",
+        func: exactdiv,
+    },
+    Builtin {
+        name: "to_float",
+        func: to_float,
+    },
+    Builtin {
+        name: "clear",
+        func: clear,
+    },
+    Builtin {
+        name: "anglemode",
+        func: anglemode,
+    },
+];
+
+pub fn lookup(name: &str) -> Option<Builtin> {
+    BUILTINS
+        .iter()
+        .find(|builtin| builtin.name == name)
+        .copied()
+}
+
+pub fn names<'a>() -> impl Iterator<Item = &'a str> {
+    BUILTINS.iter().map(|builtin| builtin.name)
+}
+
+fn expect_arity(name: &str, args: &[Value], expected: usize) -> Result<(), EvalError> {
+    if args.len() != expected {
+        return Err(EvalError::ArgumentCount {
+            name: name.to_string(),
+            expected: expected.to_string(),
+            found: args.len(),
+        });
+    }
+    Ok(())
+}
+
+fn expect_number(name: &str, value: Value) -> Result<f64, EvalError> {
+    match value.into_float() {
+        Value::Number(n) => Ok(n),
+        other => Err(EvalError::InvalidArgument {
+            name: name.to_string(),
+            expected: "number",
+            found: other.type_name(),
+        }),
+    }
+}
+
+fn number_arg(name: &str, args: Vec<Value>) -> Result<f64, EvalError> {
+    expect_arity(name, &args, 1)?;
+    expect_number(name, args.into_iter().next().unwrap())
+}
+
+fn expect_list(name: &str, value: Value) -> Result<Vec<Value>, EvalError> {
+    match value {
+        Value::List(items) => Ok(items),
+        other => Err(EvalError::InvalidArgument {
+            name: name.to_string(),
+            expected: "list",
+            found: other.type_name(),
+        }),
+    }
+}
+
+fn expect_function(name: &str, value: Value) -> Result<Value, EvalError> {
+    match value {
+        function @ (Value::Function(_) | Value::Builtin(_)) => Ok(function),
+        other => Err(EvalError::InvalidArgument {
+            name: name.to_string(),
+            expected: "function",
+            found: other.type_name(),
+        }),
+    }
+}
+
+// Names the index of the first element that is not a number
+fn expect_numbers(name: &str, value: Value) -> Result<Vec<f64>, EvalError> {
+    expect_list(name, value)?
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| match item.into_float() {
+            Value::Number(n) => Ok(n),
+            other => Err(EvalError::InvalidElement {
+                name: name.to_string(),
+                index,
+                expected: "number",
+                found: other.type_name(),
+            }),
+        })
+        .collect()
+}
+
+fn count(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    expect_arity("count", &args, 2)?;
+    let mut args = args.into_iter();
+    let items = expect_list("count", args.next().unwrap())?;
+    let needle = args.next().unwrap();
+
+    let n = items.iter().filter(|item| item.equals(&needle)).count();
+    Ok(Value::Integer(n as i64))
+}
+
+fn unique(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    expect_arity("unique", &args, 1)?;
+    let items = expect_list("unique", args.into_iter().next().unwrap())?;
+
+    let mut seen: Vec<Value> = Vec::new();
+    for item in items {
+        if !seen.iter().any(|seen| seen.equals(&item)) {
+            seen.push(item);
+        }
+    }
+    Ok(Value::List(seen))
+}
+
+fn keys(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    pair_parts("keys", args, 0)
+}
+
+fn values(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    pair_parts("values", args, 1)
+}
+
+// A list of [key, value] pairs works as a map; its entries keep the list's order
+fn pair_parts(name: &str, args: Vec<Value>, part: usize) -> Result<Value, EvalError> {
+    expect_arity(name, &args, 1)?;
+    expect_list(name, args.into_iter().next().unwrap())?
+        .into_iter()
+        .map(|entry| match entry {
+            Value::List(mut pair) if pair.len() == 2 => Ok(pair.swap_remove(part)),
+            other => Err(EvalError::InvalidArgumentValue {
+                name: name.to_string(),
+                message: format!("expected a list of [key, value] pairs, found {}", other),
+            }),
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Value::List)
+}
+
+// NaN has no place in the order, so it is always sorted last
+fn sort(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(EvalError::ArgumentCount {
+            name: "sort".to_string(),
+            expected: "1 or 2".to_string(),
+            found: args.len(),
+        });
+    }
+
+    let mut args = args.into_iter();
+    let items = args.next().unwrap();
+    let descending = match args.next() {
+        None => false,
+        Some(Value::String(order)) if order == "asc" => false,
+        Some(Value::String(order)) if order == "desc" => true,
+        Some(Value::String(order)) => {
+            return Err(EvalError::InvalidArgumentValue {
+                name: "sort".to_string(),
+                message: format!("unknown order \"{}\", expected \"asc\" or \"desc\"", order),
+            });
+        }
+        Some(other) => {
+            return Err(EvalError::InvalidArgument {
+                name: "sort".to_string(),
+                expected: "string",
+                found: other.type_name(),
+            });
+        }
+    };
+
+    let numbers = expect_numbers("sort", items)?;
+    let (nans, mut numbers): (Vec<f64>, Vec<f64>) = numbers.into_iter().partition(|n| n.is_nan());
+
+    if descending {
+        numbers.sort_by(|a, b| b.total_cmp(a));
+    } else {
+        numbers.sort_by(|a, b| a.total_cmp(b));
+    }
+    numbers.extend(nans);
+
+    Ok(Value::List(
+        numbers.into_iter().map(Value::Number).collect(),
+    ))
+}
+
+// The argument `better` prefers over every other one, kept exact; NaN wins over anything
+fn extreme(name: &str, args: Vec<Value>, better: fn(f64, f64) -> bool) -> Result<Value, EvalError> {
+    if args.is_empty() {
+        return Err(EvalError::ArgumentCount {
+            name: name.to_string(),
+            expected: "at least 1".to_string(),
+            found: 0,
+        });
+    }
+
+    let mut best: Option<(f64, Value)> = None;
+    for arg in args {
+        let n = expect_number(name, arg.clone())?;
+        match &best {
+            Some((best_n, _)) if best_n.is_nan() || !(n.is_nan() || better(n, *best_n)) => {}
+            _ => best = Some((n, arg)),
+        }
+    }
+    Ok(best.unwrap().1)
+}
+
+fn min(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    extreme("min", args, |a, b| a < b)
+}
+
+fn max(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    extreme("max", args, |a, b| a > b)
+}
+
+// Index of the first element that `better` prefers over every other one
+fn arg_extreme(
+    name: &str,
+    args: Vec<Value>,
+    better: fn(f64, f64) -> bool,
+) -> Result<Value, EvalError> {
+    expect_arity(name, &args, 1)?;
+    let numbers = expect_numbers(name, args.into_iter().next().unwrap())?;
+
+    if numbers.is_empty() {
+        return Err(EvalError::InvalidArgumentValue {
+            name: name.to_string(),
+            message: "expected a non-empty list".to_string(),
+        });
+    }
+
+    let mut best = 0;
+    for (i, n) in numbers.iter().enumerate() {
+        if better(*n, numbers[best]) {
+            best = i;
+        }
+    }
+    Ok(Value::Integer(best as i64))
+}
+
+fn argmin(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    arg_extreme("argmin", args, |a, b| a < b)
+}
+
+fn argmax(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    arg_extreme("argmax", args, |a, b| a > b)
+}
+
+// Element whose key, computed by a user function, `better` prefers; the first one on ties
+fn by_key(
+    evaluator: &mut Evaluator,
+    name: &str,
+    args: Vec<Value>,
+    better: fn(f64, f64) -> bool,
+) -> Result<Value, EvalError> {
+    expect_arity(name, &args, 2)?;
+    let mut args = args.into_iter();
+    let items = expect_list(name, args.next().unwrap())?;
+    let key = expect_function(name, args.next().unwrap())?;
+
+    let mut best: Option<(f64, Value)> = None;
+    for item in items {
+        let k = expect_number(name, evaluator.call(key.clone(), vec![item.clone()])?)?;
+        match &best {
+            Some((best_key, _)) if !better(k, *best_key) => {}
+            _ => best = Some((k, item)),
+        }
+    }
+
+    best.map(|(_, item)| item)
+        .ok_or_else(|| EvalError::InvalidArgumentValue {
+            name: name.to_string(),
+            message: "expected a non-empty list".to_string(),
+        })
+}
+
+fn max_by(evaluator: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    by_key(evaluator, "max_by", args, |a, b| a > b)
+}
+
+fn min_by(evaluator: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    by_key(evaluator, "min_by", args, |a, b| a < b)
+}
+
+// Applies `f` to each element, skipping the ones it fails on. The result is
+// [values, errors], where each error is an [index, message] pair.
+fn try_map(evaluator: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    expect_arity("try_map", &args, 2)?;
+    let mut args = args.into_iter();
+    let items = expect_list("try_map", args.next().unwrap())?;
+    let function = expect_function("try_map", args.next().unwrap())?;
+
+    let mut values = Vec::new();
+    let mut errors = Vec::new();
+    for (i, item) in items.into_iter().enumerate() {
+        match evaluator.call(function.clone(), vec![item]) {
+            Ok(value) => values.push(value),
+            Err(err) => errors.push(Value::List(vec![
+                Value::Integer(i as i64),
+                Value::String(err.to_string()),
+            ])),
+        }
+    }
+
+    Ok(Value::List(vec![Value::List(values), Value::List(errors)]))
+}
+
+fn reverse(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    expect_arity("reverse", &args, 1)?;
+    match args.into_iter().next().unwrap() {
+        Value::List(items) => Ok(Value::List(items.into_iter().rev().collect())),
+        Value::String(s) => Ok(Value::String(s.chars().rev().collect())),
+        other => Err(EvalError::InvalidArgument {
+            name: "reverse".to_string(),
+            expected: "list or string",
+            found: other.type_name(),
+        }),
+    }
+}
+
+// Without a depth every level of nesting is removed
+fn flatten(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(EvalError::ArgumentCount {
+            name: "flatten".to_string(),
+            expected: "1 or 2".to_string(),
+            found: args.len(),
+        });
+    }
+
+    let mut args = args.into_iter();
+    let items = expect_list("flatten", args.next().unwrap())?;
+    let depth = match args.next() {
+        None => None,
+        Some(value) => {
+            let depth = expect_number("flatten", value)?;
+            if depth < 0.0 || depth.fract() != 0.0 {
+                return Err(EvalError::InvalidArgumentValue {
+                    name: "flatten".to_string(),
+                    message: format!("depth must be a non-negative integer, got {}", depth),
+                });
+            }
+            Some(depth as usize)
+        }
+    };
+
+    let mut flat = Vec::new();
+    flatten_into(items, depth, &mut flat);
+    Ok(Value::List(flat))
+}
+
+fn flatten_into(items: Vec<Value>, depth: Option<usize>, flat: &mut Vec<Value>) {
+    for item in items {
+        match item {
+            Value::List(inner) if depth != Some(0) => {
+                flatten_into(inner, depth.map(|d| d - 1), flat)
+            }
+            other => flat.push(other),
+        }
+    }
+}
+
+fn sum(evaluator: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    fold_numbers(evaluator, "sum", args, Value::Integer(0), Token::Plus)
+}
+
+fn product(evaluator: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    fold_numbers(evaluator, "product", args, Value::Integer(1), Token::Star)
+}
+
+// Combines the numbers of a list with `op`, starting from the optional second
+// argument. The same arithmetic as the operator keeps integers and fractions exact.
+fn fold_numbers(
+    evaluator: &Evaluator,
+    name: &str,
+    args: Vec<Value>,
+    identity: Value,
+    op: Token,
+) -> Result<Value, EvalError> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(EvalError::ArgumentCount {
+            name: name.to_string(),
+            expected: "1 or 2".to_string(),
+            found: args.len(),
+        });
+    }
+
+    let mut args = args.into_iter();
+    let numbers = expect_list(name, args.next().unwrap())?;
+    if let Some((index, item)) = numbers
+        .iter()
+        .enumerate()
+        .find(|(_, item)| !is_number(item))
+    {
+        return Err(EvalError::InvalidElement {
+            name: name.to_string(),
+            index,
+            expected: "number",
+            found: item.type_name(),
+        });
+    }
+    let initial = match args.next() {
+        Some(value) if is_number(&value) => value,
+        Some(other) => {
+            return Err(EvalError::InvalidArgument {
+                name: name.to_string(),
+                expected: "number",
+                found: other.type_name(),
+            });
+        }
+        None => identity,
+    };
+
+    numbers
+        .into_iter()
+        .try_fold(initial, |acc, n| evaluator.binary(&op, acc, n))
+}
+
+fn is_number(value: &Value) -> bool {
+    matches!(
+        value,
+        Value::Number(_) | Value::Integer(_) | Value::Rational { .. }
+    )
+}
+
+fn clamp(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    expect_arity("clamp", &args, 3)?;
+    let mut args = args.into_iter();
+    let value = args.next().unwrap();
+    let lo = expect_number("clamp", args.next().unwrap())?;
+    let hi = expect_number("clamp", args.next().unwrap())?;
+    if lo > hi {
+        return Err(EvalError::InvalidArgumentValue {
+            name: "clamp".to_string(),
+            message: format!("lower bound {} is greater than upper bound {}", lo, hi),
+        });
+    }
+
+    match value {
+        Value::List(items) => items
+            .into_iter()
+            .map(|item| Ok(Value::Number(expect_number("clamp", item)?.max(lo).min(hi))))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::List),
+        other => Ok(Value::Number(
+            expect_number("clamp", other)?.max(lo).min(hi),
+        )),
+    }
+}
+
+// `{}` is replaced by the next argument, `{{` and `}}` are literal braces
+fn format(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    if args.is_empty() {
+        return Err(EvalError::ArgumentCount {
+            name: "format".to_string(),
+            expected: "at least 1".to_string(),
+            found: 0,
+        });
+    }
+
+    let mut args = args.into_iter();
+    let template = match args.next().unwrap() {
+        Value::String(template) => template,
+        other => {
+            return Err(EvalError::InvalidArgument {
+                name: "format".to_string(),
+                expected: "string",
+                found: other.type_name(),
+            });
+        }
+    };
+    let invalid = |message: String| EvalError::InvalidArgumentValue {
+        name: "format".to_string(),
+        message,
+    };
+
+    let mut output = String::new();
+    let mut placeholders = 0;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                output.push(c);
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                placeholders += 1;
+                match args.next() {
+                    Some(value) => output.push_str(&value.to_string()),
+                    None => {
+                        return Err(invalid(format!(
+                            "missing an argument for placeholder {}",
+                            placeholders
+                        )));
+                    }
+                }
+            }
+            ('{', _) | ('}', _) => {
+                return Err(invalid(format!(
+                    "unmatched '{}' in format string (use '{}{}' for a literal brace)",
+                    c, c, c
+                )));
+            }
+            _ => output.push(c),
+        }
+    }
+
+    let extra = args.count();
+    if extra > 0 {
+        return Err(invalid(format!(
+            "{} placeholder(s) but {} argument(s)",
+            placeholders,
+            placeholders + extra
+        )));
+    }
+
+    Ok(Value::String(output))
+}
+
+// Unlike `==`, two NaNs count as equal, so an assertion can expect NaN
+fn assert_eq(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    expect_arity("assert_eq", &args, 2)?;
+    let (left, right) = (&args[0], &args[1]);
+    if !same_value(left, right) {
+        return Err(EvalError::AssertionFailed(
+            left.to_string(),
+            right.to_string(),
+        ));
+    }
+    Ok(Value::Unit)
+}
+
+fn same_value(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x == y || (x.is_nan() && y.is_nan()),
+        (Value::List(xs), Value::List(ys)) => {
+            xs.len() == ys.len() && xs.iter().zip(ys).all(|(x, y)| same_value(x, y))
+        }
+        _ => a.equals(b),
+    }
+}
+
+fn nan(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    expect_arity("nan", &args, 0)?;
+    Ok(Value::Number(f64::NAN))
+}
+
+fn sin(evaluator: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    let x = number_arg("sin", args)?;
+    Ok(Value::Number(
+        evaluator.angle_mode.angle_to_radians(x).sin(),
+    ))
+}
+
+fn cos(evaluator: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    let x = number_arg("cos", args)?;
+    Ok(Value::Number(
+        evaluator.angle_mode.angle_to_radians(x).cos(),
+    ))
+}
+
+fn tan(evaluator: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    let x = number_arg("tan", args)?;
+    Ok(Value::Number(
+        evaluator.angle_mode.angle_to_radians(x).tan(),
+    ))
+}
+
+// Always in degrees, whatever the angle mode
+fn sind(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    math("sind", args, |x| x.to_radians().sin())
+}
+
+fn cosd(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    math("cosd", args, |x| x.to_radians().cos())
+}
+
+fn tand(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    math("tand", args, |x| x.to_radians().tan())
+}
+
+fn asin(evaluator: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    let x = number_arg("asin", args)?;
+    Ok(Value::Number(
+        evaluator.angle_mode.radians_to_angle(x.asin()),
+    ))
+}
+
+fn acos(evaluator: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    let x = number_arg("acos", args)?;
+    Ok(Value::Number(
+        evaluator.angle_mode.radians_to_angle(x.acos()),
+    ))
+}
+
+fn atan(evaluator: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    let x = number_arg("atan", args)?;
+    Ok(Value::Number(
+        evaluator.angle_mode.radians_to_angle(x.atan()),
+    ))
+}
+
+fn math(name: &str, args: Vec<Value>, f: fn(f64) -> f64) -> Result<Value, EvalError> {
+    Ok(Value::Number(f(number_arg(name, args)?)))
+}
+
+fn sqrt(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    math("sqrt", args, f64::sqrt)
+}
+
+fn abs(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    math("abs", args, f64::abs)
+}
+
+fn ln(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    math("ln", args, f64::ln)
+}
+
+fn log10(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    math("log10", args, f64::log10)
+}
+
+fn exp(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    math("exp", args, f64::exp)
+}
+
+fn floor(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    math("floor", args, f64::floor)
+}
+
+fn ceil(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    math("ceil", args, f64::ceil)
+}
+
+fn round(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    math("round", args, f64::round)
+}
+
+// Division that refuses to round: the remainder must be exactly zero
+fn exactdiv(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    expect_arity("exactdiv", &args, 2)?;
+    let mut args = args.into_iter();
+    let a = expect_number("exactdiv", args.next().unwrap())?;
+    let b = expect_number("exactdiv", args.next().unwrap())?;
+
+    if b == 0.0 {
+        return Err(EvalError::DivisionByZero(a));
+    }
+    if a % b != 0.0 {
+        return Err(EvalError::NotDivisible(a, b));
+    }
+    Ok(Value::Number(a / b))
+}
+
+fn to_float(_: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    Ok(Value::Number(number_arg("to_float", args)?))
+}
+
+fn clear(evaluator: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    expect_arity("clear", &args, 0)?;
+    evaluator.reset();
+    Ok(Value::Unit)
+}
+
+// `anglemode()` names the current mode, `anglemode("deg")` or `anglemode("rad")` switches it
+fn anglemode(evaluator: &mut Evaluator, args: Vec<Value>) -> Result<Value, EvalError> {
+    if args.is_empty() {
+        return Ok(Value::String(evaluator.angle_mode.name().to_string()));
+    }
+    expect_arity("anglemode", &args, 1)?;
+    let mode = match args.into_iter().next().unwrap() {
+        Value::String(name) => {
+            AngleMode::from_name(&name).ok_or_else(|| EvalError::InvalidArgumentValue {
+                name: "anglemode".to_string(),
+                message: format!("expected \"deg\" or \"rad\", found \"{}\"", name),
+            })?
+        }
+        other => {
+            return Err(EvalError::InvalidArgument {
+                name: "anglemode".to_string(),
+                expected: "string",
+                found: other.type_name(),
+            });
+        }
+    };
+    evaluator.angle_mode = mode;
+    Ok(Value::Unit)
+}
@@ -0,0 +1,90 @@
+// Finds names a function body refers to that are not parameters, not defined
+// and not built in. Only used for warnings, since they may be defined later.
+use crate::{
+    interpreter::{builtins, env::Env},
+    parser::{ast::Expr, symbol::Symbol},
+};
+
+pub fn undefined_names(body: &Expr, env: &Env, params: &[Symbol]) -> Vec<Symbol> {
+    // Names assigned anywhere in the body count as local to it
+    let mut bound = params.to_vec();
+    assigned_names(body, &mut bound);
+
+    let mut names = Vec::new();
+    free_names(body, env, &bound, &mut names);
+    names
+}
+
+fn free_names(expr: &Expr, env: &Env, bound: &[Symbol], names: &mut Vec<Symbol>) {
+    match expr {
+        Expr::Identifier(name) => {
+            let known = bound.contains(name)
+                || env.contains(*name)
+                || builtins::lookup(name.as_str()).is_some();
+            if !known && !names.contains(name) {
+                names.push(*name);
+            }
+        }
+
+        Expr::Function { args, body, .. } => {
+            let mut scope = bound.to_vec();
+            scope.extend(args.iter().copied());
+            free_names(body, env, &scope, names);
+        }
+
+        Expr::Lambda { param, body } => {
+            let mut scope = bound.to_vec();
+            scope.push(*param);
+            free_names(body, env, &scope, names);
+        }
+
+        _ => {
+            for child in children(expr) {
+                free_names(child, env, bound, names);
+            }
+        }
+    }
+}
+
+fn assigned_names(expr: &Expr, bound: &mut Vec<Symbol>) {
+    match expr {
+        Expr::Assignment { target, .. }
+        | Expr::Constant { target, .. }
+        | Expr::DefaultAssignment { target, .. } => bound.push(*target),
+        Expr::Function { name, .. } => bound.push(*name),
+        _ => {}
+    }
+
+    for child in children(expr) {
+        assigned_names(child, bound);
+    }
+}
+
+fn children(expr: &Expr) -> Vec<&Expr> {
+    match expr {
+        Expr::List(items) | Expr::Block(items) => items.iter().collect(),
+        Expr::Assignment { value, .. }
+        | Expr::Constant { value, .. }
+        | Expr::DefaultAssignment { value, .. } => vec![value],
+        Expr::Binary { left, right, .. } => vec![left, right],
+        Expr::Unary { expr, .. } | Expr::Postfix { expr, .. } => vec![expr],
+        Expr::If {
+            cond,
+            then_branch,
+            else_branch,
+        } => vec![cond, then_branch, else_branch],
+        Expr::Call { function, args } => {
+            let mut children = vec![function.as_ref()];
+            children.extend(args);
+            children
+        }
+        Expr::Index { target, index } => vec![target, index],
+        Expr::Range { start, end } => vec![start, end],
+        Expr::Function { body, .. } | Expr::Lambda { body, .. } => vec![body],
+        Expr::Number(_)
+        | Expr::Integer(_)
+        | Expr::String(_)
+        | Expr::Boolean(_)
+        | Expr::Identifier(_) => vec![],
+    }
+}
@@ -1,25 +1,155 @@
-use std::collections::HashMap;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet, hash_map::Entry},
+    f64::consts,
+    fmt,
+    rc::Rc,
+};
 
-use crate::interpreter::value::Value;
+use crate::{interpreter::value::Value, parser::symbol::Symbol};
 
+// A handle to a scope; clones share the same bindings. A name that is not
+// bound here is looked up in each parent in turn.
 #[derive(Clone)]
 pub struct Env {
-    pub variables: HashMap<String, Value>,
+    scope: Rc<RefCell<Scope>>,
+}
+
+#[derive(Default)]
+struct Scope {
+    variables: HashMap<Symbol, Value>,
+    constants: HashSet<Symbol>,
+    parents: Vec<Env>,
 }
 
 impl Env {
     pub fn new() -> Self {
+        // Predefined, but ordinary variables: `pi = 3` still works
+        let variables = [
+            ("pi", consts::PI),
+            ("e", consts::E),
+            ("tau", consts::TAU),
+            ("inf", f64::INFINITY),
+            // Speed of light in m/s, standard gravity in m/s^2, golden ratio
+            ("c", 299_792_458.0),
+            ("g", 9.806_65),
+            ("golden", 1.618_033_988_749_895),
+        ]
+        .into_iter()
+        .map(|(name, value)| (Symbol::intern(name), Value::Number(value)))
+        .collect();
+
+        Env::from_scope(Scope {
+            variables,
+            ..Scope::default()
+        })
+    }
+
+    // An empty scope for a function call; only the call's own bindings are allocated
+    pub fn frame(parents: Vec<Env>) -> Self {
+        Env::from_scope(Scope {
+            parents,
+            ..Scope::default()
+        })
+    }
+
+    fn from_scope(scope: Scope) -> Self {
         Env {
-            variables: HashMap::new(),
+            scope: Rc::new(RefCell::new(scope)),
         }
     }
 
-    pub fn get(&self, name: &str) -> Option<&Value> {
-        self.variables.get(name)
+    // Copies every visible binding, so later assignments do not show through
+    pub fn snapshot(&self) -> Self {
+        let mut snapshot = Scope::default();
+        self.collect_into(&mut snapshot);
+        Env::from_scope(snapshot)
     }
 
-    pub fn set(&mut self, name: String, value: Value) {
-        self.variables.insert(name, value);
+    // Nearer bindings are inserted first and win
+    fn collect_into(&self, snapshot: &mut Scope) {
+        let scope = self.scope.borrow();
+        for (&name, value) in &scope.variables {
+            if let Entry::Vacant(entry) = snapshot.variables.entry(name) {
+                entry.insert(value.clone());
+                if scope.constants.contains(&name) {
+                    snapshot.constants.insert(name);
+                }
+            }
+        }
+        for parent in &scope.parents {
+            parent.collect_into(snapshot);
+        }
+    }
+
+    // The nearest scope that binds `name`
+    fn find<T>(&self, name: Symbol, f: &impl Fn(&Scope) -> T) -> Option<T> {
+        let scope = self.scope.borrow();
+        if scope.variables.contains_key(&name) {
+            return Some(f(&scope));
+        }
+        scope.parents.iter().find_map(|parent| parent.find(name, f))
+    }
+
+    pub fn get(&self, name: Symbol) -> Option<Value> {
+        self.find(name, &|scope| scope.variables[&name].clone())
+    }
+
+    pub fn contains(&self, name: Symbol) -> bool {
+        self.find(name, &|_| ()).is_some()
+    }
+
+    // Every visible name, for suggestions
+    pub fn names(&self) -> Vec<Symbol> {
+        let mut snapshot = Scope::default();
+        self.collect_into(&mut snapshot);
+        snapshot.variables.into_keys().collect()
+    }
+
+    // Always binds in this scope, shadowing any parent binding
+    pub fn set(&mut self, name: Symbol, value: Value) {
+        self.scope.borrow_mut().variables.insert(name, value);
+    }
+
+    pub fn set_const(&mut self, name: Symbol, value: Value) {
+        let mut scope = self.scope.borrow_mut();
+        scope.constants.insert(name);
+        scope.variables.insert(name, value);
+    }
+
+    // Forgets this scope's own bindings and restores the predefined ones; parents are kept
+    pub fn clear(&mut self) {
+        let fresh = Env::new().scope.take();
+        let mut scope = self.scope.borrow_mut();
+        scope.variables = fresh.variables;
+        scope.constants = fresh.constants;
+    }
+
+    pub fn is_const(&self, name: Symbol) -> bool {
+        self.find(name, &|scope| scope.constants.contains(&name))
+            .unwrap_or(false)
+    }
+}
+
+// Two handles are equal when they are the same scope. Comparing bindings would
+// not end for a closure stored in the scope it captured.
+impl PartialEq for Env {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.scope, &other.scope)
+    }
+}
+
+// Only this scope's own names: a closure can be stored in the scope it captured,
+// so following values or parents may never end
+impl fmt::Debug for Env {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scope = self.scope.borrow();
+        let mut names: Vec<&str> = scope.variables.keys().map(|name| name.as_str()).collect();
+        names.sort_unstable();
+        f.debug_struct("Env")
+            .field("names", &names)
+            .field("parents", &scope.parents.len())
+            .finish()
     }
 }
 
@@ -27,4 +157,4 @@ impl Default for Env {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
@@ -1,13 +1,128 @@
 use thiserror::Error;
 
+use crate::lexer::token::Token;
+
 #[derive(Error, Debug, Clone)]
 pub enum EvalError {
-    #[error("Unknown variable: '{0}'")]
-    UnknownVariable(String),
+    #[error("Unknown variable: '{0}'{hint}", hint = suggestion_hint(.1))]
+    UnknownVariable(String, Option<String>),
+
+    #[error("Cannot reassign constant '{0}'")]
+    ConstantReassignment(String),
 
     #[error("Unsupported expression: {0}")]
     UnsupportedExpression(String),
 
     #[error("Invalid unary operation: '{0:?}'")]
-    InvalidUnary(crate::lexer::token::Token),
+    InvalidUnary(Token),
+
+    #[error("{name}() expected {expected} argument(s) but got {found}")]
+    ArgumentCount {
+        name: String,
+        expected: String,
+        found: usize,
+    },
+
+    #[error("{name}() expected a {expected}, found {found}")]
+    InvalidArgument {
+        name: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+
+    #[error("{name}() expected a list of {expected}s, found {found} at index {index}")]
+    InvalidElement {
+        name: String,
+        index: usize,
+        expected: &'static str,
+        found: &'static str,
+    },
+
+    #[error("Index {index} is out of bounds for a list of length {len}")]
+    IndexOutOfBounds { index: f64, len: usize },
+
+    #[error("Expected an integer, found {0}")]
+    ExpectedInteger(f64),
+
+    #[error("Division by zero: {0} cannot be divided by 0")]
+    DivisionByZero(f64),
+
+    #[error("{0} is not exactly divisible by {1}")]
+    NotDivisible(f64, f64),
+
+    #[error("{0} ^ {1} is not a real number")]
+    ComplexPower(f64, f64),
+
+    #[error("Factorial is only defined for non-negative integers, found {0}")]
+    InvalidFactorial(f64),
+
+    #[error("Cannot repeat a string a negative number of times ({0})")]
+    NegativeRepeat(f64),
+
+    #[error("Repeating a string of length {len} {count} times exceeds the limit of {limit} bytes")]
+    RepeatTooLarge {
+        len: usize,
+        count: f64,
+        limit: usize,
+    },
+
+    #[error("Cannot index into {0}")]
+    NotIndexable(&'static str),
+
+    #[error("List index must be a number or a list of numbers, found {0}")]
+    InvalidIndex(&'static str),
+
+    #[error("Range bounds must be numbers, found {0}")]
+    InvalidRange(&'static str),
+
+    #[error("Range {start}..{end} has more than {limit} elements")]
+    RangeTooLarge { start: f64, end: f64, limit: usize },
+
+    #[error("{name}(): {message}")]
+    InvalidArgumentValue { name: String, message: String },
+
+    #[error("Assertion failed: {0} is not equal to {1}")]
+    AssertionFailed(String, String),
+
+    #[error("Operands of '{op}' must be booleans, found {found}")]
+    InvalidLogicalOperand { op: Token, found: &'static str },
+
+    #[error("Maximum recursion depth exceeded ({0} nested calls)")]
+    RecursionLimit(usize),
+
+    #[error("Condition must be a boolean, found {0}")]
+    InvalidCondition(&'static str),
+
+    #[error("Cannot {} {left} and {right}", operation_name(op))]
+    InvalidOperands {
+        op: Token,
+        left: &'static str,
+        right: &'static str,
+    },
+}
+
+fn suggestion_hint(suggestion: &Option<String>) -> String {
+    match suggestion {
+        Some(name) => format!(" (did you mean '{}'?)", name),
+        None => String::new(),
+    }
+}
+
+fn operation_name(op: &Token) -> String {
+    match op {
+        Token::Plus => "add".to_string(),
+        Token::Minus => "subtract".to_string(),
+        Token::Star => "multiply".to_string(),
+        Token::Slash => "divide".to_string(),
+        Token::SlashSlash => "floor-divide".to_string(),
+        Token::Percent => "take the remainder of".to_string(),
+        Token::Caret => "exponentiate".to_string(),
+        Token::EqualEqual
+        | Token::ExclamationEqual
+        | Token::Less
+        | Token::LessEqual
+        | Token::Greater
+        | Token::GreaterEqual => "compare".to_string(),
+        _ => format!("apply '{}' to", op),
+    }
 }
@@ -1,105 +1,529 @@
 // An attempt of a simple evaluator
+use std::rc::Rc;
+
 use crate::{
     interpreter::{
+        builtins,
+        check::undefined_names,
         env::Env,
         error::EvalError,
-        value::{FunctionValue, Value},
+        fold::fold_constants,
+        value::{FunctionValue, Value, to_index},
     },
     lexer::token::Token,
     parser::ast::Expr,
 };
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AngleMode {
+    #[default]
+    Radians,
+    Degrees,
+}
+
+impl AngleMode {
+    // The names `anglemode` and the CLI accept
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "rad" | "radians" => Some(AngleMode::Radians),
+            "deg" | "degrees" => Some(AngleMode::Degrees),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AngleMode::Radians => "rad",
+            AngleMode::Degrees => "deg",
+        }
+    }
+
+    pub fn angle_to_radians(self, angle: f64) -> f64 {
+        match self {
+            AngleMode::Radians => angle,
+            AngleMode::Degrees => angle.to_radians(),
+        }
+    }
+
+    pub fn radians_to_angle(self, angle: f64) -> f64 {
+        match self {
+            AngleMode::Radians => angle,
+            AngleMode::Degrees => angle.to_degrees(),
+        }
+    }
+}
+
+// Which bindings a function body sees for names that are not its parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScopeModel {
+    // Only the caller's bindings at the time of the call
+    DynamicClone,
+    // A copy of the bindings at definition, then the caller's for anything defined later
+    #[default]
+    LexicalCapture,
+    // The defining scope itself, so later assignments there show through. A closure
+    // stored in the scope it captures keeps that scope alive for good.
+    MutableChain,
+}
+
 pub struct Evaluator {
     pub env: Env,
+    pub angle_mode: AngleMode,
+    pub scope_model: ScopeModel,
+    pub fold_constants: bool,
+    // Warn about names a function body uses before they are defined
+    pub check_definitions: bool,
+    // C-style truth values: comparisons give 1 or 0, and numbers work as conditions
+    pub bool_as_number: bool,
+    // Calls may nest this deep before evaluation stops with an error
+    pub max_call_depth: usize,
+    pub warnings: Vec<String>,
+    call_depth: usize,
+    // The top-level scope of the session, where calls look up names defined later
+    globals: Env,
 }
 
+// Deep enough for ordinary recursion, shallow enough for an 8 MiB stack even in a
+// debug build, where a call with a simple body takes about 50 KiB. Each level of
+// nesting in the body adds more, so deeply nested bodies need a bigger stack, a
+// release build or a lower `max_call_depth`.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 100;
+
 impl Evaluator {
     pub fn new() -> Self {
-        Self { env: Env::new() }
+        let env = Env::new();
+        Self {
+            globals: env.clone(),
+            env,
+            angle_mode: AngleMode::default(),
+            scope_model: ScopeModel::default(),
+            fold_constants: false,
+            check_definitions: false,
+            bool_as_number: false,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            warnings: Vec::new(),
+            call_depth: 0,
+        }
+    }
+
+    fn with_env(&self, env: Env) -> Self {
+        Self {
+            env,
+            angle_mode: self.angle_mode,
+            scope_model: self.scope_model,
+            fold_constants: self.fold_constants,
+            check_definitions: self.check_definitions,
+            bool_as_number: self.bool_as_number,
+            max_call_depth: self.max_call_depth,
+            warnings: Vec::new(),
+            call_depth: self.call_depth + 1,
+            globals: self.globals.clone(),
+        }
+    }
+
+    // Back to a fresh session: user bindings and warnings go, options are kept.
+    // Inside a call or block the top-level scope is cleared along with the local one.
+    pub fn reset(&mut self) {
+        self.env.clear();
+        self.globals.clear();
+        self.warnings.clear();
+    }
+
+    fn suggest(&self, name: &str) -> Option<String> {
+        let max_distance = name.chars().count() / 3;
+
+        let names = self.env.names();
+        names
+            .iter()
+            .map(|name| name.as_str())
+            .chain(builtins::names())
+            .map(|candidate| (edit_distance(name, candidate), candidate))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .min()
+            .map(|(_, candidate)| candidate.to_string())
     }
 
     pub fn eval(&mut self, expr: &Expr) -> Result<Value, EvalError> {
         match expr {
             Expr::Number(n) => Ok(Value::Number(*n)),
-            Expr::Identifier(name) => self
-                .env
-                .get(name)
-                .cloned()
-                .ok_or(EvalError::UnknownVariable(name.clone())),
+            Expr::Integer(n) => Ok(Value::Integer(*n)),
+            Expr::String(s) => Ok(Value::String(s.clone())),
+            Expr::Boolean(b) => Ok(Value::Boolean(*b)),
+            Expr::Identifier(name) => match self.env.get(*name) {
+                Some(value) => Ok(value),
+                None => builtins::lookup(name.as_str())
+                    .map(Value::Builtin)
+                    .ok_or_else(|| {
+                        EvalError::UnknownVariable(name.to_string(), self.suggest(name.as_str()))
+                    }),
+            },
+
+            Expr::List(items) => {
+                let values = items
+                    .iter()
+                    .map(|item| self.eval(item))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Value::List(values))
+            }
+
+            // Assignments inside a block bind in its own scope and are gone afterwards
+            Expr::Block(exprs) => {
+                let outer = self.env.clone();
+                self.env = Env::frame(vec![outer.clone()]);
+                let result = exprs
+                    .iter()
+                    .try_fold(Value::Unit, |_, expr| self.eval(expr));
+                self.env = outer;
+                result
+            }
+
+            Expr::Index { target, index } => {
+                let items = match self.eval(target)? {
+                    Value::List(items) => items,
+                    other => return Err(EvalError::NotIndexable(other.type_name())),
+                };
+                match self.eval(index)?.into_float() {
+                    Value::Number(i) => Ok(items[to_index(i, items.len())?].clone()),
+                    // A list of indices (usually a range) selects a slice
+                    Value::List(indices) => indices
+                        .into_iter()
+                        .map(|i| match i.into_float() {
+                            Value::Number(i) => Ok(items[to_index(i, items.len())?].clone()),
+                            other => Err(EvalError::InvalidIndex(other.type_name())),
+                        })
+                        .collect::<Result<Vec<_>, _>>()
+                        .map(Value::List),
+                    other => Err(EvalError::InvalidIndex(other.type_name())),
+                }
+            }
+
+            Expr::Range { start, end } => {
+                let (start, end) =
+                    match (self.eval(start)?.into_float(), self.eval(end)?.into_float()) {
+                        (Value::Number(start), Value::Number(end)) => (start, end),
+                        (Value::Number(_), other) | (other, _) => {
+                            return Err(EvalError::InvalidRange(other.type_name()));
+                        }
+                    };
+
+                for bound in [start, end] {
+                    if bound.fract() != 0.0 {
+                        return Err(EvalError::ExpectedInteger(bound));
+                    }
+                }
+                if end - start > MAX_RANGE_LEN as f64 {
+                    return Err(EvalError::RangeTooLarge {
+                        start,
+                        end,
+                        limit: MAX_RANGE_LEN,
+                    });
+                }
+
+                let values = (start as i64..end as i64).map(Value::Integer).collect();
+                Ok(Value::List(values))
+            }
 
             Expr::Unary { op, expr } => {
                 let v = self.eval(expr)?;
                 match (op, v) {
                     (Token::Minus, Value::Number(n)) => Ok(Value::Number(-n)),
                     (Token::Plus, Value::Number(n)) => Ok(Value::Number(n)),
+                    (Token::Minus, Value::Integer(n)) => Ok(n
+                        .checked_neg()
+                        .map_or(Value::Number(-(n as f64)), Value::Integer)),
+                    (Token::Plus, Value::Integer(n)) => Ok(Value::Integer(n)),
+                    (Token::Minus, Value::Rational { num, den }) => {
+                        Ok(Value::rational(-(num as i128), den as i128))
+                    }
+                    (Token::Plus, rational @ Value::Rational { .. }) => Ok(rational),
+                    (Token::Pipe, Value::Number(n)) => Ok(Value::Number(n.abs())),
+                    (Token::Pipe, Value::Integer(n)) => Ok(n
+                        .checked_abs()
+                        .map_or(Value::Number((n as f64).abs()), Value::Integer)),
+                    (Token::Pipe, Value::Rational { num, den }) => Ok(Value::Rational {
+                        num: num.abs(),
+                        den,
+                    }),
+                    (Token::Not | Token::Exclamation, Value::Boolean(b)) => Ok(Value::Boolean(!b)),
+                    (Token::Not | Token::Exclamation, Value::Number(n)) if self.bool_as_number => {
+                        Ok(Value::Number(if n == 0.0 { 1.0 } else { 0.0 }))
+                    }
+                    (Token::Not | Token::Exclamation, Value::Integer(n)) if self.bool_as_number => {
+                        Ok(Value::Integer(if n == 0 { 1 } else { 0 }))
+                    }
                     _ => Err(EvalError::InvalidUnary(op.clone())),
                 }
             }
 
+            Expr::Postfix { op, expr } => match (op, self.eval(expr)?) {
+                // Integer factorials stay exact as long as an f64 holds them exactly
+                (Token::Exclamation, Value::Integer(n)) => {
+                    let result = factorial(n as f64)?;
+                    Ok(if result <= MAX_EXACT_INTEGER as f64 {
+                        Value::Integer(result as i64)
+                    } else {
+                        Value::Number(result)
+                    })
+                }
+                (op, value) => match (op, value.into_float()) {
+                    (Token::Exclamation, Value::Number(n)) => Ok(Value::Number(factorial(n)?)),
+                    (Token::Percent, Value::Number(n)) => Ok(Value::Number(n / 100.0)),
+                    _ => Err(EvalError::InvalidUnary(op.clone())),
+                },
+            },
+
+            // `and` / `or` only evaluate the right operand when it decides the result
+            Expr::Binary {
+                left,
+                op: op @ (Token::And | Token::Or | Token::AmpAmp | Token::PipePipe),
+                right,
+            } => {
+                let short_circuit = matches!(op, Token::Or | Token::PipePipe);
+                if self.eval_logical_operand(op, left)? == short_circuit {
+                    return Ok(Value::Boolean(short_circuit));
+                }
+                self.eval_logical_operand(op, right).map(Value::Boolean)
+            }
+
             Expr::Binary { left, op, right } => {
                 // TODO: Equation solving
                 let l = self.eval(left)?;
                 let r = self.eval(right)?;
+                self.binary(op, l, r)
+            }
 
-                match (op, l, r) {
-                    (Token::Plus, Value::Number(a), Value::Number(b)) => Ok(Value::Number(a + b)),
-                    (Token::Minus, Value::Number(a), Value::Number(b)) => Ok(Value::Number(a - b)),
-                    (Token::Star, Value::Number(a), Value::Number(b)) => Ok(Value::Number(a * b)),
-                    (Token::Slash, Value::Number(a), Value::Number(b)) => Ok(Value::Number(a / b)),
-                    (Token::Caret, Value::Number(a), Value::Number(b)) => {
-                        Ok(Value::Number(a.powf(b)))
-                    }
-                    _ => Err(EvalError::UnsupportedExpression(format!(
-                        "Unsupported binary operation: {:?} {:?} {:?}",
-                        left, op, right
-                    ))),
+            Expr::If {
+                cond,
+                then_branch,
+                else_branch,
+            } => {
+                let cond = self.eval(cond)?;
+                match self.truth_value(&cond) {
+                    Some(true) => self.eval(then_branch),
+                    Some(false) => self.eval(else_branch),
+                    None => Err(EvalError::InvalidCondition(cond.type_name())),
                 }
             }
 
             Expr::Assignment { target, value } => {
+                if self.env.is_const(*target) {
+                    return Err(EvalError::ConstantReassignment(target.to_string()));
+                }
                 let evaluated = self.eval(value)?;
-                self.env.set(target.clone(), evaluated.clone());
+                self.env.set(*target, evaluated.clone());
+                Ok(evaluated)
+            }
+
+            // Only assigns when the name is not defined yet
+            Expr::DefaultAssignment { target, value } => {
+                if !self.env.contains(*target) {
+                    let evaluated = self.eval(value)?;
+                    self.env.set(*target, evaluated);
+                }
+                Ok(Value::Unit)
+            }
+
+            Expr::Constant { target, value } => {
+                if self.env.is_const(*target) {
+                    return Err(EvalError::ConstantReassignment(target.to_string()));
+                }
+                let evaluated = self.eval(value)?;
+                self.env.set_const(*target, evaluated);
                 Ok(Value::Unit)
             }
 
             Expr::Function { name, args, body } => {
+                if self.env.is_const(*name) {
+                    return Err(EvalError::ConstantReassignment(name.to_string()));
+                }
+                if self.check_definitions {
+                    // The function may call itself
+                    let mut known = args.clone();
+                    known.push(*name);
+                    for undefined in undefined_names(body, &self.env, &known) {
+                        self.warnings.push(format!(
+                            "'{}' uses '{}', which is not defined yet",
+                            name, undefined
+                        ));
+                    }
+                }
+                let body = if self.fold_constants {
+                    fold_constants(body, &self.env, args)
+                } else {
+                    *body.clone()
+                };
                 let f = Value::Function(FunctionValue {
+                    name: Some(*name),
                     params: args.clone(),
-                    body: *body.clone(),
+                    body: Rc::new(body),
+                    env: self.captured_env(),
                 });
 
-                self.env.set(name.clone(), f.clone());
+                self.env.set(*name, f.clone());
                 Ok(Value::Unit)
             }
 
+            Expr::Lambda { param, body } => {
+                let body = if self.fold_constants {
+                    fold_constants(body, &self.env, std::slice::from_ref(param))
+                } else {
+                    *body.clone()
+                };
+                Ok(Value::Function(FunctionValue {
+                    name: None,
+                    params: vec![*param],
+                    body: Rc::new(body),
+                    env: self.captured_env(),
+                }))
+            }
+
             Expr::Call { function, args } => {
                 let func_value = self.eval(function)?;
-                // TODO: Handle built-in functions
-                match func_value {
-                    Value::Function(func) => {
-                        if func.params.len() != args.len() {
-                            return Err(EvalError::UnsupportedExpression(format!(
-                                "Function expected {} arguments but got {}",
-                                func.params.len(),
-                                args.len()
-                            )));
-                        }
+                let values = args
+                    .iter()
+                    .map(|arg| self.eval(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.call(func_value, values)
+            }
+        }
+    }
 
-                        let mut new_env = self.env.clone();
-                        for (param, arg_expr) in func.params.iter().zip(args.iter()) {
-                            let arg_value = self.eval(arg_expr)?;
-                            new_env.set(param.clone(), arg_value);
-                        }
+    // The scope a function defined here keeps, according to the scope model
+    fn captured_env(&self) -> Env {
+        match self.scope_model {
+            ScopeModel::DynamicClone => Env::frame(Vec::new()),
+            ScopeModel::LexicalCapture => self.env.snapshot(),
+            ScopeModel::MutableChain => self.env.clone(),
+        }
+    }
 
-                        let mut evaluator = Evaluator { env: new_env };
-                        evaluator.eval(&func.body)
+    pub fn call(&mut self, func_value: Value, args: Vec<Value>) -> Result<Value, EvalError> {
+        match func_value {
+            Value::Builtin(builtin) => (builtin.func)(self, args),
+            Value::Function(func) => {
+                if self.call_depth >= self.max_call_depth {
+                    return Err(EvalError::RecursionLimit(self.max_call_depth));
+                }
+                if func.params.len() != args.len() {
+                    return Err(EvalError::UnsupportedExpression(format!(
+                        "Function expected {} arguments but got {}",
+                        func.params.len(),
+                        args.len()
+                    )));
+                }
+
+                // Names captured at definition win; names defined later (the function
+                // itself, or ones it calls) are still found at the top level. The
+                // caller's own locals are never visible.
+                let parents = match self.scope_model {
+                    ScopeModel::DynamicClone => vec![self.env.clone()],
+                    ScopeModel::LexicalCapture | ScopeModel::MutableChain => {
+                        vec![func.env.clone(), self.globals.clone()]
                     }
-                    _ => Err(EvalError::UnsupportedExpression(format!(
-                        "Attempted to call a non-function value: {:?}",
-                        func_value
-                    ))),
+                };
+                let mut new_env = Env::frame(parents);
+                // The function can always call itself, whatever its name is bound to now
+                if let Some(name) = func.name {
+                    new_env.set(name, Value::Function(func.clone()));
                 }
+                for (&param, arg) in func.params.iter().zip(args) {
+                    new_env.set(param, arg);
+                }
+
+                let mut evaluator = self.with_env(new_env);
+                let result = evaluator.eval(&func.body);
+                // Warnings from definitions inside the body belong to the caller too
+                self.warnings.append(&mut evaluator.warnings);
+                result
             }
+            _ => Err(EvalError::UnsupportedExpression(format!(
+                "Attempted to call a non-function value: {}",
+                func_value
+            ))),
+        }
+    }
+
+    fn eval_logical_operand(&mut self, op: &Token, expr: &Expr) -> Result<bool, EvalError> {
+        let value = self.eval(expr)?;
+        self.truth_value(&value)
+            .ok_or_else(|| EvalError::InvalidLogicalOperand {
+                op: op.clone(),
+                found: value.type_name(),
+            })
+    }
+
+    // A binary operator on evaluated operands; `and` and `or` are handled before this
+    pub(crate) fn binary(&self, op: &Token, l: Value, r: Value) -> Result<Value, EvalError> {
+        if let Some(integer) = integer_arithmetic(op, &l, &r) {
+            return Ok(integer);
+        }
+        if let Some(exact) = exact_arithmetic(op, &l, &r) {
+            return Ok(exact);
+        }
+        if *op == Token::Caret
+            && let Some(root) = odd_root_power(&l, &r)
+        {
+            return Ok(Value::Number(root));
+        }
+        let (l, r) = (l.into_float(), r.into_float());
+
+        let result = match (op, l, r) {
+            (Token::Plus, Value::Number(a), Value::Number(b)) => Ok(Value::Number(a + b)),
+            (Token::Plus, Value::String(a), Value::String(b)) => Ok(Value::String(a + &b)),
+            (Token::Minus, Value::Number(a), Value::Number(b)) => Ok(Value::Number(a - b)),
+            (Token::Star, Value::Number(a), Value::Number(b)) => Ok(Value::Number(a * b)),
+            (Token::Star, Value::String(s), Value::Number(n)) => {
+                Ok(Value::String(s.repeat(repeat_count(&s, n)?)))
+            }
+            (
+                Token::Slash | Token::SlashSlash | Token::Percent,
+                Value::Number(a),
+                Value::Number(0.0),
+            ) => Err(EvalError::DivisionByZero(a)),
+            (Token::Slash, Value::Number(a), Value::Number(b)) => Ok(Value::Number(a / b)),
+            (Token::SlashSlash, Value::Number(a), Value::Number(b)) => {
+                Ok(Value::Number((a / b).floor()))
+            }
+            // Euclidean remainder: never negative, so -7 % 3 is 2
+            (Token::Percent, Value::Number(a), Value::Number(b)) => {
+                Ok(Value::Number(a.rem_euclid(b)))
+            }
+            (Token::Caret, Value::Number(a), Value::Number(b)) => power(a, b),
+            // Numbers are compared exactly, without tolerance
+            (Token::EqualEqual, a, b) if is_equatable(&a, &b) => Ok(Value::Boolean(a.equals(&b))),
+            (Token::ExclamationEqual, a, b) if is_equatable(&a, &b) => {
+                Ok(Value::Boolean(!a.equals(&b)))
+            }
+            (Token::Less, Value::Number(a), Value::Number(b)) => Ok(Value::Boolean(a < b)),
+            (Token::LessEqual, Value::Number(a), Value::Number(b)) => Ok(Value::Boolean(a <= b)),
+            (Token::Greater, Value::Number(a), Value::Number(b)) => Ok(Value::Boolean(a > b)),
+            (Token::GreaterEqual, Value::Number(a), Value::Number(b)) => Ok(Value::Boolean(a >= b)),
+            (Token::In, needle, Value::List(items)) => Ok(Value::Boolean(
+                items.iter().any(|item| item.equals(&needle)),
+            )),
+            (_, l, r) => Err(EvalError::InvalidOperands {
+                op: op.clone(),
+                left: l.type_name(),
+                right: r.type_name(),
+            }),
+        };
+
+        match result {
+            Ok(Value::Boolean(b)) if self.bool_as_number && is_comparison(op) => {
+                Ok(Value::Number(if b { 1.0 } else { 0.0 }))
+            }
+            result => result,
+        }
+    }
+
+    // Numbers are only true or false with bool_as_number, where anything but 0 is true
+    fn truth_value(&self, value: &Value) -> Option<bool> {
+        match value {
+            Value::Boolean(b) => Some(*b),
+            Value::Number(n) if self.bool_as_number => Some(*n != 0.0),
+            Value::Integer(n) if self.bool_as_number => Some(*n != 0),
+            Value::Rational { .. } if self.bool_as_number => Some(true),
+            _ => None,
         }
     }
 }
@@ -108,4 +532,189 @@ impl Default for Evaluator {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+
+fn is_comparison(op: &Token) -> bool {
+    matches!(
+        op,
+        Token::EqualEqual
+            | Token::ExclamationEqual
+            | Token::Less
+            | Token::LessEqual
+            | Token::Greater
+            | Token::GreaterEqual
+    )
+}
+
+fn is_equatable(a: &Value, b: &Value) -> bool {
+    matches!(
+        (a, b),
+        (Value::Number(_), Value::Number(_))
+            | (Value::String(_), Value::String(_))
+            | (Value::Boolean(_), Value::Boolean(_))
+            | (Value::List(_), Value::List(_))
+    )
+}
+
+// Longest list a range may build, so huge ranges fail instead of aborting
+const MAX_RANGE_LEN: usize = 1 << 24;
+
+// Every integer up to 2^53 is exactly representable as an f64
+const MAX_EXACT_INTEGER: i128 = 1 << 53;
+
+// Floats are never exact, so mixing one in makes the result a float
+fn as_fraction(value: &Value) -> Option<(i128, i128)> {
+    match value {
+        Value::Integer(n) => Some((*n as i128, 1)),
+        Value::Rational { num, den } => Some((*num as i128, *den as i128)),
+        _ => None,
+    }
+}
+
+// Arithmetic on two integers stays an integer. Division is left to
+// exact_arithmetic, and overflow or a negative power to the float rules.
+fn integer_arithmetic(op: &Token, l: &Value, r: &Value) -> Option<Value> {
+    let (&Value::Integer(a), &Value::Integer(b)) = (l, r) else {
+        return None;
+    };
+    let n = match op {
+        Token::Plus => a.checked_add(b),
+        Token::Minus => a.checked_sub(b),
+        Token::Star => a.checked_mul(b),
+        // Rounds toward negative infinity, like the float `//`
+        Token::SlashSlash if b != 0 => {
+            let quotient = a.checked_div(b)?;
+            Some(if a % b != 0 && (a < 0) != (b < 0) {
+                quotient - 1
+            } else {
+                quotient
+            })
+        }
+        Token::Percent if b != 0 => a.checked_rem_euclid(b),
+        Token::Caret => a.checked_pow(u32::try_from(b).ok()?),
+        _ => None,
+    }?;
+    Some(Value::Integer(n))
+}
+
+// Dividing integers that do not divide evenly, and arithmetic on the resulting
+// rationals, stays exact. None means the float rules apply.
+fn exact_arithmetic(op: &Token, l: &Value, r: &Value) -> Option<Value> {
+    let involves_rational =
+        matches!(l, Value::Rational { .. }) || matches!(r, Value::Rational { .. });
+    if !involves_rational && *op != Token::Slash {
+        return None;
+    }
+
+    let (a, b) = as_fraction(l)?;
+    let (c, d) = as_fraction(r)?;
+    match op {
+        Token::Plus => Some(Value::rational((a * d).checked_add(c * b)?, b * d)),
+        Token::Minus => Some(Value::rational((a * d).checked_sub(c * b)?, b * d)),
+        Token::Star => Some(Value::rational(a * c, b * d)),
+        Token::Slash if c != 0 => Some(Value::rational(a * d, b * c)),
+        Token::Caret if d == 1 && c.unsigned_abs() <= 64 => {
+            let exponent = c.unsigned_abs() as u32;
+            let (num, den) = (a.checked_pow(exponent)?, b.checked_pow(exponent)?);
+            match c {
+                0.. => Some(Value::rational(num, den)),
+                _ if num != 0 => Some(Value::rational(den, num)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+// A negative base has a real power when the exponent is a fraction with an odd
+// denominator: (-8)^(1/3) is -2 and (-8)^(2/3) is 4
+fn odd_root_power(base: &Value, exponent: &Value) -> Option<f64> {
+    let Value::Rational { num, den } = *exponent else {
+        return None;
+    };
+    let Value::Number(base) = base.clone().into_float() else {
+        return None;
+    };
+    if base >= 0.0 || den % 2 == 0 {
+        return None;
+    }
+
+    let magnitude = (-base).powf(num as f64 / den as f64);
+    Some(if num % 2 == 0 { magnitude } else { -magnitude })
+}
+
+fn power(base: f64, exponent: f64) -> Result<Value, EvalError> {
+    if let Some(exact) = integer_pow(base, exponent) {
+        return Ok(Value::Number(exact));
+    }
+    if exponent.fract() == 0.0 && exponent.abs() <= i32::MAX as f64 {
+        return Ok(Value::Number(base.powi(exponent as i32)));
+    }
+
+    // Only a negative base with a fractional exponent gets here as NaN
+    let result = base.powf(exponent);
+    if result.is_nan() && !base.is_nan() && !exponent.is_nan() {
+        return Err(EvalError::ComplexPower(base, exponent));
+    }
+    Ok(Value::Number(result))
+}
+
+// Exact power of two non-negative integers, or None when powf has to be used
+fn integer_pow(base: f64, exponent: f64) -> Option<f64> {
+    if base.fract() != 0.0 || exponent.fract() != 0.0 || base < 0.0 || exponent < 0.0 {
+        return None;
+    }
+    if base > MAX_EXACT_INTEGER as f64 || exponent > u32::MAX as f64 {
+        return None;
+    }
+
+    let result = (base as i128).checked_pow(exponent as u32)?;
+    (result <= MAX_EXACT_INTEGER).then_some(result as f64)
+}
+
+fn factorial(n: f64) -> Result<f64, EvalError> {
+    if n.fract() != 0.0 || n < 0.0 {
+        return Err(EvalError::InvalidFactorial(n));
+    }
+    // Anything above 170! overflows to infinity anyway
+    if n > 170.0 {
+        return Ok(f64::INFINITY);
+    }
+    Ok((1..=n as u32).map(f64::from).product())
+}
+
+// Longest string a repetition may build, so huge counts fail instead of aborting
+const MAX_STRING_BYTES: usize = 1 << 28;
+
+fn repeat_count(s: &str, n: f64) -> Result<usize, EvalError> {
+    if n.fract() != 0.0 {
+        return Err(EvalError::ExpectedInteger(n));
+    }
+    if n < 0.0 {
+        return Err(EvalError::NegativeRepeat(n));
+    }
+    if !s.is_empty() && n > (MAX_STRING_BYTES / s.len()) as f64 {
+        return Err(EvalError::RepeatTooLarge {
+            len: s.len(),
+            count: n,
+            limit: MAX_STRING_BYTES,
+        });
+    }
+    Ok(n as usize)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
@@ -0,0 +1,163 @@
+// Inlines `const` globals into function bodies and folds the resulting
+// arithmetic on literals. Mutable globals are left as lookups.
+use crate::{
+    interpreter::{env::Env, value::Value},
+    lexer::token::Token,
+    parser::{ast::Expr, symbol::Symbol},
+};
+
+pub fn fold_constants(expr: &Expr, env: &Env, params: &[Symbol]) -> Expr {
+    match expr {
+        Expr::Identifier(name) if !params.contains(name) && env.is_const(*name) => {
+            match env.get(*name) {
+                Some(Value::Number(n)) => Expr::Number(n),
+                Some(Value::Integer(n)) => Expr::Integer(n),
+                _ => expr.clone(),
+            }
+        }
+
+        Expr::Binary { left, op, right } => {
+            let left = fold_constants(left, env, params);
+            let right = fold_constants(right, env, params);
+
+            if let (Expr::Integer(a), Expr::Integer(b)) = (&left, &right)
+                && let Some(n) = fold_integers(*a, op, *b)
+            {
+                return Expr::Integer(n);
+            }
+
+            // A float on either side makes the result a float
+            let floats = match (&left, &right) {
+                (Expr::Integer(_), Expr::Integer(_)) => None,
+                (Expr::Number(a), Expr::Number(b)) => Some((*a, *b)),
+                (Expr::Number(a), Expr::Integer(b)) => Some((*a, *b as f64)),
+                (Expr::Integer(a), Expr::Number(b)) => Some((*a as f64, *b)),
+                _ => None,
+            };
+            match (floats, op) {
+                (Some((a, b)), Token::Plus) => Expr::Number(a + b),
+                (Some((a, b)), Token::Minus) => Expr::Number(a - b),
+                (Some((a, b)), Token::Star) => Expr::Number(a * b),
+                (Some((a, b)), Token::Slash) if b != 0.0 => Expr::Number(a / b),
+                // A power with no real value is left for the evaluator to report
+                (Some((a, b)), Token::Caret) if !a.powf(b).is_nan() => Expr::Number(a.powf(b)),
+                _ => Expr::Binary {
+                    left: Box::new(left),
+                    op: op.clone(),
+                    right: Box::new(right),
+                },
+            }
+        }
+
+        Expr::Unary { op, expr } => match (op, fold_constants(expr, env, params)) {
+            (Token::Minus, Expr::Number(n)) => Expr::Number(-n),
+            (Token::Plus, Expr::Number(n)) => Expr::Number(n),
+            (Token::Minus, Expr::Integer(n)) if n != i64::MIN => Expr::Integer(-n),
+            (Token::Plus, Expr::Integer(n)) => Expr::Integer(n),
+            (_, folded) => Expr::Unary {
+                op: op.clone(),
+                expr: Box::new(folded),
+            },
+        },
+
+        Expr::Postfix { op, expr } => Expr::Postfix {
+            op: op.clone(),
+            expr: Box::new(fold_constants(expr, env, params)),
+        },
+
+        Expr::List(items) => Expr::List(
+            items
+                .iter()
+                .map(|item| fold_constants(item, env, params))
+                .collect(),
+        ),
+
+        Expr::Block(exprs) => Expr::Block(
+            exprs
+                .iter()
+                .map(|expr| fold_constants(expr, env, params))
+                .collect(),
+        ),
+
+        Expr::Assignment { target, value } => Expr::Assignment {
+            target: *target,
+            value: Box::new(fold_constants(value, env, params)),
+        },
+
+        Expr::Constant { target, value } => Expr::Constant {
+            target: *target,
+            value: Box::new(fold_constants(value, env, params)),
+        },
+
+        Expr::DefaultAssignment { target, value } => Expr::DefaultAssignment {
+            target: *target,
+            value: Box::new(fold_constants(value, env, params)),
+        },
+
+        Expr::If {
+            cond,
+            then_branch,
+            else_branch,
+        } => Expr::If {
+            cond: Box::new(fold_constants(cond, env, params)),
+            then_branch: Box::new(fold_constants(then_branch, env, params)),
+            else_branch: Box::new(fold_constants(else_branch, env, params)),
+        },
+
+        Expr::Call { function, args } => Expr::Call {
+            function: function.clone(),
+            args: args
+                .iter()
+                .map(|arg| fold_constants(arg, env, params))
+                .collect(),
+        },
+
+        Expr::Index { target, index } => Expr::Index {
+            target: Box::new(fold_constants(target, env, params)),
+            index: Box::new(fold_constants(index, env, params)),
+        },
+
+        Expr::Range { start, end } => Expr::Range {
+            start: Box::new(fold_constants(start, env, params)),
+            end: Box::new(fold_constants(end, env, params)),
+        },
+
+        Expr::Function { name, args, body } => {
+            let mut scope = params.to_vec();
+            scope.extend(args.iter().copied());
+            Expr::Function {
+                name: *name,
+                args: args.clone(),
+                body: Box::new(fold_constants(body, env, &scope)),
+            }
+        }
+
+        Expr::Lambda { param, body } => {
+            let mut scope = params.to_vec();
+            scope.push(*param);
+            Expr::Lambda {
+                param: *param,
+                body: Box::new(fold_constants(body, env, &scope)),
+            }
+        }
+
+        Expr::Number(_)
+        | Expr::Integer(_)
+        | Expr::String(_)
+        | Expr::Boolean(_)
+        | Expr::Identifier(_) => expr.clone(),
+    }
+}
+
+// Integer arithmetic that stays an integer. Division that does not come out even,
+// negative powers and overflow are left to the evaluator.
+fn fold_integers(a: i64, op: &Token, b: i64) -> Option<i64> {
+    match op {
+        Token::Plus => a.checked_add(b),
+        Token::Minus => a.checked_sub(b),
+        Token::Star => a.checked_mul(b),
+        Token::Slash if b != 0 && a % b == 0 => a.checked_div(b),
+        Token::Caret => a.checked_pow(u32::try_from(b).ok()?),
+        _ => None,
+    }
+}
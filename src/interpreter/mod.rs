@@ -1,4 +1,8 @@
 pub mod value;
 pub mod env;
 pub mod eval;
-pub mod error;
\ No newline at end of file
+pub mod error;
+pub mod builtins;
+pub mod fold;
+pub mod check;
+pub mod tests;
\ No newline at end of file
@@ -0,0 +1,2203 @@
+#[cfg(test)]
+mod interpreter_tests {
+    use crate::interpreter::{
+        env::Env,
+        error::EvalError,
+        eval::{AngleMode, DEFAULT_MAX_CALL_DEPTH, Evaluator, ScopeModel},
+        value::{Value, to_index},
+    };
+    use crate::lexer::{
+        token::Token,
+        tokenizer::{DoubleSlashMode, Lexer},
+    };
+    use crate::parser::{ast::Expr, pratt::Parser};
+
+    fn eval(input: &str) -> Result<Value, EvalError> {
+        eval_in(&mut Evaluator::new(), input)
+    }
+
+    fn eval_in(evaluator: &mut Evaluator, input: &str) -> Result<Value, EvalError> {
+        let mut lexer = Lexer::new(input);
+        let tokens = lexer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let exprs = parser.parse().unwrap();
+
+        let mut result = Value::Unit;
+        for expr in exprs {
+            result = evaluator.eval(&expr)?;
+        }
+        Ok(result)
+    }
+
+    #[test]
+    fn test_number_literal() {
+        assert_eq!(eval("42").unwrap(), Value::Integer(42));
+        assert_eq!(eval("3.5").unwrap(), Value::Number(3.5));
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(eval("1 + 2").unwrap(), Value::Integer(3));
+        assert_eq!(eval("5 - 8").unwrap(), Value::Integer(-3));
+        assert_eq!(eval("4 * 2.5").unwrap(), Value::Number(10.0));
+        assert_eq!(eval("9 / 3").unwrap(), Value::Integer(3));
+        assert_eq!(eval("4.5 / 2").unwrap(), Value::Number(2.25));
+        assert_eq!(eval("2 ^ 10").unwrap(), Value::Integer(1024));
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(eval("1 + 2 * 3").unwrap(), Value::Integer(7));
+        assert_eq!(eval("(1 + 2) * 3").unwrap(), Value::Integer(9));
+        assert_eq!(eval("2 * 3 ^ 2").unwrap(), Value::Integer(18));
+        assert_eq!(eval("10 - 4 - 3").unwrap(), Value::Integer(3));
+    }
+
+    #[test]
+    fn test_right_associative_power() {
+        assert_eq!(eval("2 ^ 3 ^ 2").unwrap(), Value::Integer(512));
+    }
+
+    #[test]
+    fn test_unary_operators() {
+        assert_eq!(eval("-5 + 2").unwrap(), Value::Integer(-3));
+        assert_eq!(eval("+5").unwrap(), Value::Integer(5));
+        assert_eq!(eval("--5").unwrap(), Value::Integer(5));
+    }
+
+    #[test]
+    fn test_implicit_multiplication() {
+        assert_eq!(eval("x = 3\n2x").unwrap(), Value::Integer(6));
+        assert_eq!(eval("x = 3\n2(x + 1)").unwrap(), Value::Integer(8));
+        assert_eq!(eval("(1 + 1)(2 + 2)").unwrap(), Value::Integer(8));
+    }
+
+    #[test]
+    fn test_assignment() {
+        assert_eq!(eval("x = 5").unwrap(), Value::Integer(5));
+        assert_eq!(eval("x = 5\ny = x + 3\ny").unwrap(), Value::Integer(8));
+        assert_eq!(eval("x = 1; x = x + 1; x").unwrap(), Value::Integer(2));
+    }
+
+    #[test]
+    fn test_chained_assignment() {
+        assert_eq!(eval("a = b = 5").unwrap(), Value::Integer(5));
+        assert_eq!(eval("a = b = 5\na + b").unwrap(), Value::Integer(10));
+        assert_eq!(eval("(x = 3) * 2").unwrap(), Value::Integer(6));
+        assert_eq!(eval("y = (x = 4) + 1\nx * y").unwrap(), Value::Integer(20));
+    }
+
+    #[test]
+    fn test_function_body_assignment_is_local() {
+        assert_eq!(eval("f(x) = h = x * 2\nf(3)").unwrap(), Value::Integer(6));
+        assert!(matches!(
+            eval("f(x) = h = x\nf(3)\nh").unwrap_err(),
+            EvalError::UnknownVariable(name, _) if name == "h"
+        ));
+        assert_eq!(
+            eval("h = 1\nf(x) = h = x\nf(3)\nh").unwrap(),
+            Value::Integer(1)
+        );
+    }
+
+    #[test]
+    fn test_function_definition_and_call() {
+        assert_eq!(eval("f(x) = x * 2\nf(21)").unwrap(), Value::Integer(42));
+        assert_eq!(
+            eval("add(a, b) = a + b\nadd(2, 3)").unwrap(),
+            Value::Integer(5)
+        );
+        assert_eq!(eval("one() = 1\none() + 1").unwrap(), Value::Integer(2));
+    }
+
+    #[test]
+    fn test_function_parameters_do_not_leak() {
+        let err = eval("f(y) = y\nf(1)\ny").unwrap_err();
+        assert!(matches!(err, EvalError::UnknownVariable(name, _) if name == "y"));
+    }
+
+    #[test]
+    fn test_function_composition() {
+        let result = eval("f(x) = x + 1\ng(x) = 2 * f(x)\ng(f(1))").unwrap();
+        assert_eq!(result, Value::Integer(6));
+    }
+
+    #[test]
+    fn test_unknown_variable() {
+        let err = eval("x + 1").unwrap_err();
+        assert!(matches!(err, EvalError::UnknownVariable(name, _) if name == "x"));
+    }
+
+    #[test]
+    fn test_wrong_argument_count() {
+        let err = eval("f(x) = x\nf(1, 2)").unwrap_err();
+        assert!(matches!(err, EvalError::UnsupportedExpression(_)));
+    }
+
+    #[test]
+    fn test_calling_a_number() {
+        let err = eval("x = 1\nx()").unwrap_err();
+        assert!(matches!(err, EvalError::UnsupportedExpression(_)));
+    }
+
+    #[test]
+    fn test_comparisons() {
+        assert_eq!(eval("1 < 2").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("2 <= 1").unwrap(), Value::Boolean(false));
+        assert_eq!(eval("1 + 1 == 2").unwrap(), Value::Boolean(true));
+    }
+
+    #[test]
+    fn test_condition_must_be_boolean() {
+        let err = eval("1 ? 2 : 3").unwrap_err();
+        assert!(matches!(err, EvalError::InvalidCondition("number")));
+    }
+
+    #[test]
+    fn test_bool_as_number() {
+        assert!(matches!(
+            eval("(3 > 2) + 1").unwrap_err(),
+            EvalError::InvalidOperands {
+                left: "boolean",
+                right: "number",
+                ..
+            }
+        ));
+
+        let mut evaluator = Evaluator::new();
+        evaluator.bool_as_number = true;
+        assert_eq!(
+            eval_in(&mut evaluator, "(3 > 2) + 1").unwrap(),
+            Value::Number(2.0)
+        );
+        assert_eq!(
+            eval_in(&mut evaluator, "x = -4\n(x > 0) * 5").unwrap(),
+            Value::Number(0.0)
+        );
+        assert_eq!(
+            eval_in(&mut evaluator, "1 == 1").unwrap(),
+            Value::Number(1.0)
+        );
+        // Numbers work as conditions and logical operands, anything but 0 is true
+        assert_eq!(
+            eval_in(&mut evaluator, "2 ? 10 : 20").unwrap(),
+            Value::Integer(10)
+        );
+        assert_eq!(
+            eval_in(&mut evaluator, "(1 < 2) and (0 == 0)").unwrap(),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            eval_in(&mut evaluator, "!(1 > 2)").unwrap(),
+            Value::Number(1.0)
+        );
+        // Only comparisons are affected
+        assert_eq!(
+            eval_in(&mut evaluator, "2 in [1, 2]").unwrap(),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            eval_in(&mut evaluator, "true").unwrap(),
+            Value::Boolean(true)
+        );
+    }
+
+    #[test]
+    fn test_percent() {
+        assert_eq!(eval("50%").unwrap(), Value::Number(0.5));
+        assert_eq!(eval("50% * 200").unwrap(), Value::Number(100.0));
+        assert_eq!(eval("200 * 15%").unwrap(), Value::Number(30.0));
+        assert_eq!(eval("7 % 3").unwrap(), Value::Integer(1));
+        assert_eq!(eval("x = 25\nx%").unwrap(), Value::Number(0.25));
+        assert!(matches!(
+            eval("\"a\"%").unwrap_err(),
+            EvalError::InvalidUnary(Token::Percent)
+        ));
+    }
+
+    #[test]
+    fn test_raw_identifiers() {
+        assert_eq!(eval("`and` = 2\n`and` * 3").unwrap(), Value::Integer(6));
+        assert_eq!(
+            eval("`my var` = 4\n`my var` + 1").unwrap(),
+            Value::Integer(5)
+        );
+        // A backticked plain name is the same variable
+        assert_eq!(eval("x = 7\n`x`").unwrap(), Value::Integer(7));
+        assert_eq!(eval("`if`(n) = n + 1\n`if`(1)").unwrap(), Value::Integer(2));
+    }
+
+    #[test]
+    fn test_binary_error_names_operand_types() {
+        let err = eval("f(x) = x\nf + 1").unwrap_err();
+        assert!(matches!(
+            err,
+            EvalError::InvalidOperands {
+                left: "function",
+                right: "number",
+                ..
+            }
+        ));
+        assert_eq!(err.to_string(), "Cannot add function and number");
+    }
+
+    #[test]
+    fn test_membership() {
+        assert_eq!(eval("3 in [1, 2, 3]").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("5 in [1, 2, 3]").unwrap(), Value::Boolean(false));
+        assert_eq!(eval("5 not in [1, 2, 3]").unwrap(), Value::Boolean(true));
+        assert_eq!(
+            eval("x = 2\nx + 1 not in [1, 2, 3]").unwrap(),
+            Value::Boolean(false)
+        );
+        assert_eq!(eval("[1] in [[1], [2]]").unwrap(), Value::Boolean(true));
+    }
+
+    #[test]
+    fn test_membership_requires_list() {
+        let err = eval("3 in 3").unwrap_err();
+        assert!(matches!(
+            err,
+            EvalError::InvalidOperands {
+                op: Token::In,
+                right: "number",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_recursive_factorial_with_conditional() {
+        let result = eval("fact(n) = n <= 1 ? 1 : n * fact(n-1)\nfact(5)").unwrap();
+        assert_eq!(result, Value::Integer(120));
+    }
+
+    #[test]
+    fn test_count() {
+        assert_eq!(eval("count([1, 2, 2, 3], 2)").unwrap(), Value::Integer(2));
+        assert_eq!(eval("count([1, 2, 3], 4)").unwrap(), Value::Integer(0));
+        assert_eq!(
+            eval("count([[1], [1], 1], [1])").unwrap(),
+            Value::Integer(2)
+        );
+        assert_eq!(eval("count([], 1)").unwrap(), Value::Integer(0));
+    }
+
+    #[test]
+    fn test_unique() {
+        assert_eq!(
+            eval("unique([1, 2, 2, 3, 1])").unwrap(),
+            Value::List(vec![
+                Value::Integer(1),
+                Value::Integer(2),
+                Value::Integer(3),
+            ])
+        );
+        assert_eq!(
+            eval("unique([3, 1, 3])").unwrap(),
+            Value::List(vec![Value::Integer(3), Value::Integer(1)])
+        );
+        assert_eq!(eval("unique([])").unwrap(), Value::List(vec![]));
+    }
+
+    #[test]
+    fn test_builtin_argument_errors() {
+        let err = eval("count([1, 2])").unwrap_err();
+        assert!(matches!(err, EvalError::ArgumentCount { found: 1, .. }));
+
+        let err = eval("unique(5)").unwrap_err();
+        assert!(matches!(
+            err,
+            EvalError::InvalidArgument {
+                expected: "list",
+                found: "number",
+                ..
+            }
+        ));
+    }
+
+    fn number(value: Value) -> f64 {
+        match value {
+            Value::Number(n) => n,
+            Value::Integer(n) => n as f64,
+            other => panic!("Expected a number, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_trig_in_radians_by_default() {
+        assert!((number(eval("sin(90)").unwrap()) - 0.893997).abs() < 1e-6);
+        assert_eq!(eval("cos(0)").unwrap(), Value::Number(1.0));
+    }
+
+    #[test]
+    fn test_trig_in_degrees() {
+        let mut evaluator = Evaluator::new();
+        evaluator.angle_mode = AngleMode::Degrees;
+
+        assert!((number(eval_in(&mut evaluator, "sin(90)").unwrap()) - 1.0).abs() < 1e-12);
+        assert!((number(eval_in(&mut evaluator, "cos(60)").unwrap()) - 0.5).abs() < 1e-12);
+        assert!((number(eval_in(&mut evaluator, "tan(45)").unwrap()) - 1.0).abs() < 1e-12);
+        assert!((number(eval_in(&mut evaluator, "asin(1)").unwrap()) - 90.0).abs() < 1e-12);
+        assert!(
+            (number(eval_in(&mut evaluator, "f(x) = sin(x)\nf(30)").unwrap()) - 0.5).abs() < 1e-12
+        );
+    }
+
+    #[test]
+    fn test_anglemode_builtin() {
+        let mut evaluator = Evaluator::new();
+        assert_eq!(
+            eval_in(&mut evaluator, "anglemode()").unwrap(),
+            Value::String("rad".to_string())
+        );
+
+        assert_eq!(
+            eval_in(&mut evaluator, "anglemode(\"deg\")").unwrap(),
+            Value::Unit
+        );
+        assert_eq!(evaluator.angle_mode, AngleMode::Degrees);
+        assert!((number(eval_in(&mut evaluator, "sin(90)").unwrap()) - 1.0).abs() < 1e-12);
+        assert!((number(eval_in(&mut evaluator, "acos(0)").unwrap()) - 90.0).abs() < 1e-12);
+
+        eval_in(&mut evaluator, "anglemode(\"radians\")").unwrap();
+        assert_eq!(evaluator.angle_mode, AngleMode::Radians);
+        assert_eq!(
+            eval_in(&mut evaluator, "cos(0)").unwrap(),
+            Value::Number(1.0)
+        );
+
+        // A function's mode ends with the call
+        eval_in(&mut evaluator, "f(x) = (anglemode(\"deg\"); sin(x))").unwrap();
+        assert!((number(eval_in(&mut evaluator, "f(90)").unwrap()) - 1.0).abs() < 1e-12);
+        assert_eq!(evaluator.angle_mode, AngleMode::Radians);
+
+        assert!(matches!(
+            eval_in(&mut evaluator, "anglemode(\"grad\")"),
+            Err(EvalError::InvalidArgumentValue { .. })
+        ));
+        assert!(matches!(
+            eval_in(&mut evaluator, "anglemode(1)"),
+            Err(EvalError::InvalidArgument { .. })
+        ));
+        assert!(matches!(
+            eval_in(&mut evaluator, "anglemode(\"deg\", \"rad\")"),
+            Err(EvalError::ArgumentCount { .. })
+        ));
+    }
+
+    #[test]
+    fn test_degree_trig_builtins() {
+        assert_eq!(Evaluator::new().angle_mode, AngleMode::Radians);
+        assert_eq!(eval("sind(90)").unwrap(), Value::Number(1.0));
+        assert!((number(eval("cosd(60)").unwrap()) - 0.5).abs() < 1e-12);
+        assert!((number(eval("tand(45)").unwrap()) - 1.0).abs() < 1e-12);
+
+        // Unaffected by the angle mode
+        let mut evaluator = Evaluator::new();
+        evaluator.angle_mode = AngleMode::Degrees;
+        assert_eq!(
+            eval_in(&mut evaluator, "sind(90)").unwrap(),
+            Value::Number(1.0)
+        );
+    }
+
+    fn function_body(evaluator: &Evaluator, name: &str) -> Expr {
+        match evaluator.env.get(name.into()) {
+            Some(Value::Function(function)) => (*function.body).clone(),
+            other => panic!("Expected a function, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_constant_folding_inlines_constants_only() {
+        let mut evaluator = Evaluator::new();
+        evaluator.fold_constants = true;
+        eval_in(
+            &mut evaluator,
+            "const g = 9.81\nh = 2\nf(m) = m * (2 * g)\nk(m) = m * h",
+        )
+        .unwrap();
+
+        assert_eq!(
+            function_body(&evaluator, "f"),
+            Expr::Binary {
+                left: Box::new(Expr::Identifier("m".into())),
+                op: Token::Star,
+                right: Box::new(Expr::Number(19.62)),
+            }
+        );
+        assert_eq!(
+            function_body(&evaluator, "k"),
+            Expr::Binary {
+                left: Box::new(Expr::Identifier("m".into())),
+                op: Token::Star,
+                right: Box::new(Expr::Identifier("h".into())),
+            }
+        );
+        assert_eq!(
+            eval_in(&mut evaluator, "f(0.5)").unwrap(),
+            Value::Number(9.81)
+        );
+    }
+
+    #[test]
+    fn test_constant_folding_respects_parameters() {
+        let mut evaluator = Evaluator::new();
+        evaluator.fold_constants = true;
+        eval_in(&mut evaluator, "const g = 9.81\nf(g) = g + 1").unwrap();
+
+        assert_eq!(eval_in(&mut evaluator, "f(1)").unwrap(), Value::Integer(2));
+    }
+
+    #[test]
+    fn test_constant_cannot_be_reassigned() {
+        let err = eval("const g = 9.81\ng = 10").unwrap_err();
+        assert!(matches!(err, EvalError::ConstantReassignment(name) if name == "g"));
+        assert_eq!(eval("const g = 9.81\ng").unwrap(), Value::Number(9.81));
+    }
+
+    #[test]
+    fn test_clamp_scalar() {
+        assert_eq!(eval("clamp(-1, 0, 1)").unwrap(), Value::Number(0.0));
+        assert_eq!(eval("clamp(0.5, 0, 1)").unwrap(), Value::Number(0.5));
+        assert_eq!(eval("clamp(2, 0, 1)").unwrap(), Value::Number(1.0));
+        assert_eq!(eval("clamp(5, 3, 3)").unwrap(), Value::Number(3.0));
+    }
+
+    #[test]
+    fn test_clamp_inverted_bounds() {
+        let err = eval("clamp(0.5, 1, 0)").unwrap_err();
+        assert!(matches!(err, EvalError::InvalidArgumentValue { .. }));
+        assert_eq!(
+            err.to_string(),
+            "clamp(): lower bound 1 is greater than upper bound 0"
+        );
+        assert!(eval("clamp([], 2, -2)").is_err());
+    }
+
+    #[test]
+    fn test_clamp_list() {
+        assert_eq!(
+            eval("clamp([-1, 0.5, 2], 0, 1)").unwrap(),
+            Value::List(vec![
+                Value::Number(0.0),
+                Value::Number(0.5),
+                Value::Number(1.0),
+            ])
+        );
+        assert_eq!(eval("clamp([], 0, 1)").unwrap(), Value::List(vec![]));
+
+        let err = eval("clamp([1, [2]], 0, 1)").unwrap_err();
+        assert!(matches!(
+            err,
+            EvalError::InvalidArgument {
+                expected: "number",
+                found: "list",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_assert_eq_treats_nans_as_equal() {
+        assert_eq!(eval("assert_eq(nan(), nan())").unwrap(), Value::Unit);
+        assert_eq!(eval("nan() == nan()").unwrap(), Value::Boolean(false));
+        assert_eq!(eval("nan() != nan()").unwrap(), Value::Boolean(true));
+
+        assert_eq!(
+            eval("assert_eq([1, nan()], [1, nan()])").unwrap(),
+            Value::Unit
+        );
+        assert_eq!(eval("assert_eq(\"a\", \"a\")").unwrap(), Value::Unit);
+    }
+
+    #[test]
+    fn test_assert_eq_failure() {
+        let err = eval("assert_eq(1 + 1, 3)").unwrap_err();
+        assert!(matches!(err, EvalError::AssertionFailed(..)));
+        assert_eq!(err.to_string(), "Assertion failed: 2 is not equal to 3");
+
+        assert!(eval("assert_eq(nan(), 0)").is_err());
+        assert!(eval("assert_eq([1], [1, 2])").is_err());
+        assert!(matches!(
+            eval("assert_eq(1)").unwrap_err(),
+            EvalError::ArgumentCount { .. }
+        ));
+    }
+
+    #[test]
+    fn test_unknown_variable_suggestion() {
+        let err = eval("length = 3\nlenght").unwrap_err();
+        assert!(matches!(
+            &err,
+            EvalError::UnknownVariable(name, Some(suggestion))
+                if name == "lenght" && suggestion == "length"
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Unknown variable: 'lenght' (did you mean 'length'?)"
+        );
+
+        let err = eval("uniqe([1])").unwrap_err();
+        assert!(err.to_string().ends_with("(did you mean 'unique'?)"));
+
+        let err = eval("length = 3\nwidth").unwrap_err();
+        assert!(matches!(err, EvalError::UnknownVariable(_, None)));
+    }
+
+    #[test]
+    fn test_negative_index() {
+        assert_eq!(eval("[1, 2, 3][-1]").unwrap(), Value::Integer(3));
+        assert_eq!(eval("[1, 2, 3][-2]").unwrap(), Value::Integer(2));
+        assert_eq!(eval("[1, 2, 3][-3]").unwrap(), Value::Integer(1));
+        assert_eq!(eval("[1, 2, 3][0]").unwrap(), Value::Integer(1));
+
+        let err = eval("[1, 2, 3][-4]").unwrap_err();
+        assert!(matches!(err, EvalError::IndexOutOfBounds { len: 3, .. }));
+        assert_eq!(
+            err.to_string(),
+            "Index -4 is out of bounds for a list of length 3"
+        );
+    }
+
+    #[test]
+    fn test_range() {
+        assert_eq!(
+            eval("1..4").unwrap(),
+            Value::List(vec![
+                Value::Integer(1),
+                Value::Integer(2),
+                Value::Integer(3),
+            ])
+        );
+        assert_eq!(eval("3..3").unwrap(), Value::List(vec![]));
+        assert!(matches!(
+            eval("1..2.5").unwrap_err(),
+            EvalError::ExpectedInteger(_)
+        ));
+        assert!(matches!(
+            eval("0..1e10").unwrap_err(),
+            EvalError::RangeTooLarge { .. }
+        ));
+        assert!(matches!(
+            eval("-1e300..1e300").unwrap_err(),
+            EvalError::RangeTooLarge { .. }
+        ));
+    }
+
+    #[test]
+    fn test_slice() {
+        assert_eq!(
+            eval("[10, 20, 30, 40][1..3]").unwrap(),
+            Value::List(vec![Value::Integer(20), Value::Integer(30)])
+        );
+        assert_eq!(
+            eval("[10, 20, 30, 40][-2..0]").unwrap(),
+            Value::List(vec![Value::Integer(30), Value::Integer(40)])
+        );
+        assert_eq!(eval("[10, 20][1..1]").unwrap(), Value::List(vec![]));
+
+        let err = eval("[10, 20, 30, 40][2..6]").unwrap_err();
+        assert!(matches!(err, EvalError::IndexOutOfBounds { len: 4, .. }));
+    }
+
+    fn numbers(values: &[f64]) -> Value {
+        Value::List(values.iter().copied().map(Value::Number).collect())
+    }
+
+    fn integers(values: &[i64]) -> Value {
+        Value::List(values.iter().copied().map(Value::Integer).collect())
+    }
+
+    #[test]
+    fn test_sort() {
+        assert_eq!(eval("sort([3, 1, 2])").unwrap(), numbers(&[1.0, 2.0, 3.0]));
+        assert_eq!(
+            eval("sort([3, 1, 2], \"asc\")").unwrap(),
+            numbers(&[1.0, 2.0, 3.0])
+        );
+        assert_eq!(
+            eval("sort([3, 1, 2], \"desc\")").unwrap(),
+            numbers(&[3.0, 2.0, 1.0])
+        );
+        assert_eq!(eval("sort([])").unwrap(), numbers(&[]));
+    }
+
+    #[test]
+    fn test_sort_duplicates() {
+        assert_eq!(
+            eval("sort([2, -1, 2, 0, -1])").unwrap(),
+            numbers(&[-1.0, -1.0, 0.0, 2.0, 2.0])
+        );
+        assert_eq!(
+            eval("sort([2, -1, 2, 0, -1], \"desc\")").unwrap(),
+            numbers(&[2.0, 2.0, 0.0, -1.0, -1.0])
+        );
+    }
+
+    #[test]
+    fn test_sort_errors() {
+        let err = eval("sort([3, [1], 2])").unwrap_err();
+        assert!(matches!(
+            err,
+            EvalError::InvalidElement {
+                index: 1,
+                expected: "number",
+                found: "list",
+                ..
+            }
+        ));
+
+        let err = eval("sort([3, 1], \"up\")").unwrap_err();
+        assert!(matches!(err, EvalError::InvalidArgumentValue { .. }));
+    }
+
+    #[test]
+    fn test_reverse() {
+        assert_eq!(eval("reverse([1, 2, 3])").unwrap(), integers(&[3, 2, 1]));
+        assert_eq!(eval("reverse([])").unwrap(), numbers(&[]));
+        assert_eq!(
+            eval("reverse(\"abc\")").unwrap(),
+            Value::String("cba".into())
+        );
+        assert_eq!(eval("reverse(\"àé\")").unwrap(), Value::String("éà".into()));
+        assert!(matches!(
+            eval("reverse(1)").unwrap_err(),
+            EvalError::InvalidArgument { .. }
+        ));
+    }
+
+    #[test]
+    fn test_comparing_unit_names_the_type() {
+        let err = eval("(f(x) = 1) == 1").unwrap_err();
+        assert!(matches!(
+            err,
+            EvalError::InvalidOperands {
+                op: Token::EqualEqual,
+                left: "unit",
+                right: "number",
+            }
+        ));
+        assert_eq!(err.to_string(), "Cannot compare unit and number");
+
+        let err = eval("1 < (f(x) = 1)").unwrap_err();
+        assert_eq!(err.to_string(), "Cannot compare number and unit");
+    }
+
+    #[test]
+    fn test_modulo() {
+        assert_eq!(eval("7 % 3").unwrap(), Value::Integer(1));
+        assert_eq!(eval("6 % 3").unwrap(), Value::Integer(0));
+        assert_eq!(eval("1 + 7 % 3 * 2").unwrap(), Value::Integer(3));
+    }
+
+    #[test]
+    fn test_modulo_negative_operands() {
+        assert_eq!(eval("-7 % 3").unwrap(), Value::Integer(2));
+        assert_eq!(eval("7 % -3").unwrap(), Value::Integer(1));
+        assert_eq!(eval("-7 % -3").unwrap(), Value::Integer(2));
+    }
+
+    #[test]
+    fn test_modulo_fractional_operands() {
+        assert_eq!(eval("5.5 % 2").unwrap(), Value::Number(1.5));
+        assert_eq!(eval("-5.5 % 2").unwrap(), Value::Number(0.5));
+        assert_eq!(eval("7 % 2.5").unwrap(), Value::Number(2.0));
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        assert!(matches!(
+            eval("5 / 0").unwrap_err(),
+            EvalError::DivisionByZero(n) if n == 5.0
+        ));
+        assert!(matches!(
+            eval("5 % 0").unwrap_err(),
+            EvalError::DivisionByZero(n) if n == 5.0
+        ));
+        assert_eq!(
+            eval("x = 0\n0 / x").unwrap_err().to_string(),
+            "Division by zero: 0 cannot be divided by 0"
+        );
+        assert!(eval("5 / -0").is_err());
+        assert_eq!(eval("5 / -0.5").unwrap(), Value::Number(-10.0));
+    }
+
+    #[test]
+    fn test_min_max() {
+        assert_eq!(eval("max(1, 5)").unwrap(), Value::Integer(5));
+        assert_eq!(eval("min(1, 5)").unwrap(), Value::Integer(1));
+        assert_eq!(eval("max(1, 5, 3)").unwrap(), Value::Integer(5));
+        assert_eq!(eval("min(4, -2, 3)").unwrap(), Value::Integer(-2));
+        assert_eq!(eval("max(7)").unwrap(), Value::Integer(7));
+        assert_eq!(
+            eval("min(1/2, 1/3)").unwrap(),
+            Value::Rational { num: 1, den: 3 }
+        );
+        assert_eq!(eval("max(sqrt(-1), 1)").unwrap().to_string(), "NaN");
+        assert_eq!(eval("min(1, sqrt(-1))").unwrap().to_string(), "NaN");
+    }
+
+    #[test]
+    fn test_min_max_errors() {
+        assert!(matches!(
+            eval("max()").unwrap_err(),
+            EvalError::ArgumentCount { found: 0, .. }
+        ));
+        assert!(matches!(
+            eval("min(1, \"a\")").unwrap_err(),
+            EvalError::InvalidArgument { .. }
+        ));
+    }
+
+    #[test]
+    fn test_user_max_overrides_builtin() {
+        assert_eq!(
+            eval("max(a, b) = a + b\nmax(1, 5)").unwrap(),
+            Value::Integer(6)
+        );
+    }
+
+    #[test]
+    fn test_argmin_argmax() {
+        assert_eq!(eval("argmax([3, 1, 4, 1, 5])").unwrap(), Value::Integer(4));
+        assert_eq!(eval("argmin([3, 1, 4, 1, 5])").unwrap(), Value::Integer(1));
+        assert_eq!(eval("argmax([7])").unwrap(), Value::Integer(0));
+    }
+
+    #[test]
+    fn test_argmin_argmax_ties_return_first_index() {
+        assert_eq!(eval("argmax([5, 2, 5])").unwrap(), Value::Integer(0));
+        assert_eq!(eval("argmin([3, 1, 4, 1])").unwrap(), Value::Integer(1));
+    }
+
+    #[test]
+    fn test_argmin_argmax_errors() {
+        assert!(matches!(
+            eval("argmax([])").unwrap_err(),
+            EvalError::InvalidArgumentValue { .. }
+        ));
+        assert!(matches!(
+            eval("argmin([1, \"a\"])").unwrap_err(),
+            EvalError::InvalidElement {
+                index: 1,
+                expected: "number",
+                found: "string",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_each_comparison_operator() {
+        assert_eq!(eval("2 == 2").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("2 == 3").unwrap(), Value::Boolean(false));
+        assert_eq!(eval("2 != 3").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("2 != 2").unwrap(), Value::Boolean(false));
+        assert_eq!(eval("2 < 3").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("3 < 3").unwrap(), Value::Boolean(false));
+        assert_eq!(eval("3 <= 3").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("4 <= 3").unwrap(), Value::Boolean(false));
+        assert_eq!(eval("4 > 3").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("3 > 3").unwrap(), Value::Boolean(false));
+        assert_eq!(eval("3 >= 3").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("2 >= 3").unwrap(), Value::Boolean(false));
+    }
+
+    #[test]
+    fn test_equality_is_exact() {
+        assert_eq!(eval("0.1 + 0.2 == 0.3").unwrap(), Value::Boolean(false));
+        assert_eq!(eval("0.5 + 0.25 == 0.75").unwrap(), Value::Boolean(true));
+    }
+
+    #[test]
+    fn test_comparing_booleans_and_other_values() {
+        assert_eq!(eval("(1 < 2) == (3 > 2)").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("(1 < 2) != (3 < 2)").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("\"a\" == \"a\"").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("[1, 2] == [1, 2]").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("[1, 2] != [2, 1]").unwrap(), Value::Boolean(true));
+
+        let err = eval("(1 < 2) == 1").unwrap_err();
+        assert_eq!(err.to_string(), "Cannot compare boolean and number");
+        let err = eval("(1 < 2) < (2 < 3)").unwrap_err();
+        assert_eq!(err.to_string(), "Cannot compare boolean and boolean");
+    }
+
+    #[test]
+    fn test_math_builtins() {
+        assert_eq!(eval("sqrt(16)").unwrap(), Value::Number(4.0));
+        assert_eq!(eval("abs(-3)").unwrap(), Value::Number(3.0));
+        assert_eq!(eval("ln(1)").unwrap(), Value::Number(0.0));
+        assert_eq!(eval("log10(1000)").unwrap(), Value::Number(3.0));
+        assert_eq!(eval("exp(0)").unwrap(), Value::Number(1.0));
+        assert_eq!(eval("floor(2.7)").unwrap(), Value::Number(2.0));
+        assert_eq!(eval("ceil(2.1)").unwrap(), Value::Number(3.0));
+        assert_eq!(eval("round(2.5)").unwrap(), Value::Number(3.0));
+        assert!(number(eval("sqrt(-1)").unwrap()).is_nan());
+    }
+
+    #[test]
+    fn test_math_builtin_errors() {
+        let err = eval("sqrt(1, 2)").unwrap_err();
+        assert_eq!(err.to_string(), "sqrt() expected 1 argument(s) but got 2");
+
+        let err = eval("abs([1])").unwrap_err();
+        assert!(matches!(
+            err,
+            EvalError::InvalidArgument {
+                expected: "number",
+                found: "list",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_user_function_shadows_builtin() {
+        assert_eq!(eval("sin(x) = x * 2\nsin(3)").unwrap(), Value::Integer(6));
+        assert_eq!(eval("sqrt = 5\nsqrt + 1").unwrap(), Value::Integer(6));
+    }
+
+    #[test]
+    fn test_string_repetition() {
+        assert_eq!(
+            eval("\"ab\" * 3").unwrap(),
+            Value::String("ababab".to_string())
+        );
+        assert_eq!(eval("\"x\" * 0").unwrap(), Value::String(String::new()));
+    }
+
+    #[test]
+    fn test_string_repetition_errors() {
+        let err = eval("\"ab\" * -1").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Cannot repeat a string a negative number of times (-1)"
+        );
+        assert!(matches!(
+            eval("\"ab\" * 1.5").unwrap_err(),
+            EvalError::ExpectedInteger(_)
+        ));
+        assert!(matches!(
+            eval("\"ab\" * \"c\"").unwrap_err(),
+            EvalError::InvalidOperands { .. }
+        ));
+    }
+
+    #[test]
+    fn test_string_repetition_too_large() {
+        assert!(matches!(
+            eval("\"ab\" * 1e19").unwrap_err(),
+            EvalError::RepeatTooLarge { len: 2, .. }
+        ));
+        assert!(matches!(
+            eval("\"ab\" * 1e300 * 0").unwrap_err(),
+            EvalError::RepeatTooLarge { .. }
+        ));
+        assert_eq!(eval("\"\" * 1e19").unwrap(), Value::String(String::new()));
+    }
+
+    #[test]
+    fn test_predefined_constants() {
+        assert_eq!(eval("pi").unwrap(), Value::Number(std::f64::consts::PI));
+        assert_eq!(eval("e").unwrap(), Value::Number(std::f64::consts::E));
+        assert_eq!(eval("tau").unwrap(), Value::Number(std::f64::consts::TAU));
+        assert_eq!(eval("inf").unwrap(), Value::Number(f64::INFINITY));
+        assert_eq!(
+            eval("r = 2\n2 * pi * r").unwrap(),
+            Value::Number(4.0 * std::f64::consts::PI)
+        );
+    }
+
+    #[test]
+    fn test_overriding_predefined_constants() {
+        let mut evaluator = Evaluator::new();
+        eval_in(&mut evaluator, "pi = 3").unwrap();
+
+        assert_eq!(evaluator.env.get("pi".into()), Some(Value::Integer(3)));
+        assert_eq!(
+            eval_in(&mut evaluator, "2 * pi").unwrap(),
+            Value::Integer(6)
+        );
+    }
+
+    #[test]
+    fn test_integer_power_is_exact() {
+        assert_eq!(eval("3^30").unwrap(), Value::Integer(205891132094649));
+        assert_eq!(eval("3^33").unwrap(), Value::Integer(5559060566555523));
+        assert_eq!(eval("2^10").unwrap(), Value::Integer(1024));
+        assert_eq!(eval("0^0").unwrap(), Value::Integer(1));
+        assert_eq!(eval("1^4000000000").unwrap(), Value::Integer(1));
+    }
+
+    #[test]
+    fn test_power_falls_back_to_floats() {
+        assert_eq!(eval("2^-1").unwrap(), Value::Number(0.5));
+        assert_eq!(eval("4^0.5").unwrap(), Value::Number(2.0));
+        assert_eq!(eval("(-2)^3").unwrap(), Value::Integer(-8));
+        assert_eq!(eval("2^64").unwrap(), Value::Number(2f64.powi(64)));
+    }
+
+    #[test]
+    fn test_power_of_negative_bases() {
+        assert_eq!(eval("(-2)^3").unwrap(), Value::Integer(-8));
+        assert_eq!(eval("2^10").unwrap(), Value::Integer(1024));
+        assert_eq!(eval("(-2.5)^2").unwrap(), Value::Number(6.25));
+        assert_eq!(eval("(-0.5)^-3").unwrap(), Value::Number(-8.0));
+
+        // Odd roots of negative numbers are real
+        let approx = |input: &str, expected: f64| match eval(input).unwrap() {
+            Value::Number(n) => assert!((n - expected).abs() < 1e-12, "{} = {}", input, n),
+            other => panic!("expected a number, got {:?}", other),
+        };
+        assert_eq!(eval("(-8)^(1/3)").unwrap(), Value::Number(-2.0));
+        approx("(-8)^(2/3)", 4.0);
+        approx("(-27)^(-1/3)", -1.0 / 3.0);
+
+        let err = eval("(-8)^0.5").unwrap_err();
+        assert!(matches!(err, EvalError::ComplexPower(-8.0, 0.5)));
+        assert_eq!(err.to_string(), "-8 ^ 0.5 is not a real number");
+        assert!(matches!(
+            eval("(-1)^(1/2)").unwrap_err(),
+            EvalError::ComplexPower(..)
+        ));
+    }
+
+    #[test]
+    fn test_floor_division() {
+        let floor_div = |input: &str| {
+            let tokens = Lexer::new(input)
+                .with_double_slash(DoubleSlashMode::FloorDiv)
+                .tokenize()
+                .unwrap();
+            let exprs = Parser::new(tokens).parse().unwrap();
+            Evaluator::new().eval(&exprs[0])
+        };
+
+        assert_eq!(floor_div("7 // 2").unwrap(), Value::Integer(3));
+        assert_eq!(floor_div("-7 // 2").unwrap(), Value::Integer(-4));
+        assert_eq!(floor_div("7.5 // 2 # comment").unwrap(), Value::Number(3.0));
+        assert_eq!(floor_div("(1/2) // (1/4)").unwrap(), Value::Number(2.0));
+        assert!(matches!(
+            floor_div("7 // 0").unwrap_err(),
+            EvalError::DivisionByZero(7.0)
+        ));
+        assert_eq!(
+            floor_div("\"a\" // 2").unwrap_err().to_string(),
+            "Cannot floor-divide string and number"
+        );
+    }
+
+    #[test]
+    fn test_clear_inside_function_or_block_clears_the_session() {
+        assert!(matches!(
+            eval("x = 5; { clear() }; x"),
+            Err(EvalError::UnknownVariable(..))
+        ));
+        assert!(matches!(
+            eval("(y = 1; clear(); y)"),
+            Err(EvalError::UnknownVariable(..))
+        ));
+
+        let mut evaluator = Evaluator::new();
+        eval_in(&mut evaluator, "x = 5").unwrap();
+        eval_in(&mut evaluator, "f(y) = clear()").unwrap();
+        assert_eq!(eval_in(&mut evaluator, "f(1)").unwrap(), Value::Unit);
+        assert!(matches!(
+            eval_in(&mut evaluator, "x"),
+            Err(EvalError::UnknownVariable(..))
+        ));
+        assert!(matches!(
+            eval_in(&mut evaluator, "f(1)"),
+            Err(EvalError::UnknownVariable(..))
+        ));
+        assert!(matches!(
+            eval_in(&mut evaluator, "clear(1)"),
+            Err(EvalError::ArgumentCount { .. })
+        ));
+    }
+
+    #[test]
+    fn test_factorial() {
+        assert_eq!(eval("5!").unwrap(), Value::Integer(120));
+        assert_eq!(eval("0!").unwrap(), Value::Integer(1));
+        assert_eq!(eval("3! + 1").unwrap(), Value::Integer(7));
+        assert_eq!(eval("2^3!").unwrap(), Value::Integer(64));
+        assert_eq!(eval("n = 4\nn!").unwrap(), Value::Integer(24));
+        // Past 2^53 the result is no longer exact and becomes a float
+        assert_eq!(eval("20!").unwrap(), Value::Number(2432902008176640000.0));
+        assert_eq!(eval("171!").unwrap(), Value::Number(f64::INFINITY));
+    }
+
+    #[test]
+    fn test_factorial_errors() {
+        let err = eval("2.5!").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Factorial is only defined for non-negative integers, found 2.5"
+        );
+        assert!(matches!(
+            eval("(-1)!").unwrap_err(),
+            EvalError::InvalidFactorial(_)
+        ));
+        assert!(matches!(
+            eval("[1]!").unwrap_err(),
+            EvalError::InvalidUnary(Token::Exclamation)
+        ));
+    }
+
+    #[test]
+    fn test_block_returns_last_value() {
+        assert_eq!(eval("(1; 2)").unwrap(), Value::Integer(2));
+        assert_eq!(
+            eval("f(x) = (y = x * 2; y + 1)\nf(5)").unwrap(),
+            Value::Integer(11)
+        );
+    }
+
+    #[test]
+    fn test_block_assignments_stay_in_function_scope() {
+        let mut evaluator = Evaluator::new();
+        eval_in(&mut evaluator, "f(x) = (y = x * 2; y + 1)\nf(5)").unwrap();
+        assert_eq!(evaluator.env.get("y".into()), None);
+    }
+
+    #[test]
+    fn test_brace_block() {
+        assert_eq!(eval("{ a = 1; b = 2; a + b }").unwrap(), Value::Integer(3));
+        assert_eq!(
+            eval("f(x) = {\n    y = x * 2;\n    y + 1\n}\nf(5)").unwrap(),
+            Value::Integer(11)
+        );
+        assert_eq!(eval("{}").unwrap(), Value::Unit);
+    }
+
+    #[test]
+    fn test_multiline_brace_block() {
+        assert_eq!(
+            eval("f(x) = {\n y = x*2\n y + 1\n}\nf(2)").unwrap(),
+            Value::Integer(5)
+        );
+        assert_eq!(
+            eval("total = {\n  a = 1\n\n  b = a + 1\n  a + b\n}\ntotal * 2").unwrap(),
+            Value::Integer(6)
+        );
+    }
+
+    #[test]
+    fn test_block_bindings_do_not_leak() {
+        let mut evaluator = Evaluator::new();
+        eval_in(&mut evaluator, "x = 1").unwrap();
+        assert_eq!(
+            eval_in(&mut evaluator, "{ x = 10; y = x + 1; y }").unwrap(),
+            Value::Integer(11)
+        );
+        assert_eq!(
+            eval_in(&mut evaluator, "(z = 2; z)").unwrap(),
+            Value::Integer(2)
+        );
+
+        assert_eq!(evaluator.env.get("x".into()), Some(Value::Integer(1)));
+        assert_eq!(evaluator.env.get("y".into()), None);
+        assert_eq!(evaluator.env.get("z".into()), None);
+        // The scope is restored even when the block fails
+        assert!(eval_in(&mut evaluator, "{ w = 1; 1 / 0 }").is_err());
+        assert_eq!(evaluator.env.get("w".into()), None);
+    }
+
+    #[test]
+    fn test_absolute_value_bars() {
+        assert_eq!(eval("|-5| == 5").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("|3 - 10|").unwrap(), Value::Integer(7));
+        assert_eq!(eval("||-2| - 5|").unwrap(), Value::Integer(3));
+        assert_eq!(eval("2|-3| + (1)|-4|").unwrap(), Value::Integer(10));
+        assert_eq!(eval("|-1/3|").unwrap(), Value::Rational { num: 1, den: 3 });
+        assert_eq!(eval("x = -4\n|x| + |2x|").unwrap(), Value::Integer(12));
+        assert!(matches!(
+            eval("|\"a\"|").unwrap_err(),
+            EvalError::InvalidUnary(Token::Pipe)
+        ));
+    }
+
+    #[test]
+    fn test_prefix_not() {
+        assert_eq!(eval("!(1 < 2)").unwrap(), Value::Boolean(false));
+        assert_eq!(eval("!(3! == 5)").unwrap(), Value::Boolean(true));
+        assert!(matches!(
+            eval("!5").unwrap_err(),
+            EvalError::InvalidUnary(Token::Exclamation)
+        ));
+    }
+
+    #[test]
+    fn test_prefix_not_on_comparisons() {
+        assert_eq!(eval("!(1 < 2)").unwrap(), Value::Boolean(false));
+        assert_eq!(eval("!(2 < 1)").unwrap(), Value::Boolean(true));
+        // Without parentheses `!` applies to the number, not the comparison
+        assert!(matches!(
+            eval("!1 < 2").unwrap_err(),
+            EvalError::InvalidUnary(Token::Exclamation)
+        ));
+        assert_eq!(eval("x = -1\n!(x > 0)").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("!!true").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("!(1 < 2) or true").unwrap(), Value::Boolean(true));
+        assert!(matches!(
+            eval("!\"yes\"").unwrap_err(),
+            EvalError::InvalidUnary(Token::Exclamation)
+        ));
+        assert!(matches!(
+            eval("!(1 + 1)").unwrap_err(),
+            EvalError::InvalidUnary(Token::Exclamation)
+        ));
+    }
+
+    #[test]
+    fn test_to_index() {
+        assert_eq!(to_index(0.0, 3).unwrap(), 0);
+        assert_eq!(to_index(2.0, 3).unwrap(), 2);
+        assert_eq!(to_index(-1.0, 3).unwrap(), 2);
+        assert_eq!(to_index(-3.0, 3).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_to_index_errors() {
+        assert!(matches!(
+            to_index(1.5, 3).unwrap_err(),
+            EvalError::ExpectedInteger(_)
+        ));
+        assert!(matches!(
+            to_index(3.0, 3).unwrap_err(),
+            EvalError::IndexOutOfBounds { len: 3, .. }
+        ));
+        assert!(matches!(
+            to_index(-4.0, 3).unwrap_err(),
+            EvalError::IndexOutOfBounds { len: 3, .. }
+        ));
+        assert!(to_index(0.0, 0).is_err());
+    }
+
+    #[test]
+    fn test_logical_operators() {
+        assert_eq!(eval("true and false").unwrap(), Value::Boolean(false));
+        assert_eq!(eval("true or false").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("1 < 2 and 2 < 3").unwrap(), Value::Boolean(true));
+        assert_eq!(
+            eval("false or true and false").unwrap(),
+            Value::Boolean(false)
+        );
+    }
+
+    #[test]
+    fn test_logical_operators_short_circuit() {
+        assert_eq!(
+            eval("false and undefined_var").unwrap(),
+            Value::Boolean(false)
+        );
+        assert_eq!(eval("true or 1 / 0 == 1").unwrap(), Value::Boolean(true));
+        assert!(matches!(
+            eval("true and undefined_var").unwrap_err(),
+            EvalError::UnknownVariable(..)
+        ));
+    }
+
+    #[test]
+    fn test_symbolic_logical_operators() {
+        assert_eq!(eval("true && false").unwrap(), Value::Boolean(false));
+        assert_eq!(eval("false || true").unwrap(), Value::Boolean(true));
+        assert_eq!(
+            eval("false || true && false").unwrap(),
+            Value::Boolean(false)
+        );
+        assert_eq!(eval("1 < 2 && 2 < 3").unwrap(), Value::Boolean(true));
+
+        // The right operand would divide by zero if it were evaluated
+        assert_eq!(eval("false && (1/0 > 0)").unwrap(), Value::Boolean(false));
+        assert_eq!(eval("true || (1/0 > 0)").unwrap(), Value::Boolean(true));
+        assert!(matches!(
+            eval("true && (1/0 > 0)").unwrap_err(),
+            EvalError::DivisionByZero(_)
+        ));
+
+        let err = eval("1 && true").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Operands of '&&' must be booleans, found number"
+        );
+        assert!(matches!(
+            eval("false || 0").unwrap_err(),
+            EvalError::InvalidLogicalOperand { .. }
+        ));
+    }
+
+    #[test]
+    fn test_logical_operand_must_be_boolean() {
+        let err = eval("1 and true").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Operands of 'and' must be booleans, found number"
+        );
+        let err = eval("false or \"yes\"").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Operands of 'or' must be booleans, found string"
+        );
+    }
+
+    #[test]
+    fn test_string_concatenation() {
+        assert_eq!(
+            eval(r#""foo" + "bar""#).unwrap(),
+            Value::String("foobar".to_string())
+        );
+        assert_eq!(
+            eval("a = \"x\\ty\"\na + \"\\n\"").unwrap(),
+            Value::String("x\ty\n".to_string())
+        );
+        assert!(matches!(
+            eval(r#""a" + 1"#).unwrap_err(),
+            EvalError::InvalidOperands {
+                left: "string",
+                right: "number",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_physical_constants() {
+        assert!((number(eval("golden").unwrap()) - 1.618).abs() < 1e-3);
+        assert_eq!(eval("golden^2 - golden").unwrap(), Value::Number(1.0));
+        assert_eq!(eval("c").unwrap(), Value::Number(299792458.0));
+        assert_eq!(eval("g").unwrap(), Value::Number(9.80665));
+    }
+
+    #[test]
+    fn test_shadowing_physical_constants() {
+        let mut evaluator = Evaluator::new();
+        eval_in(&mut evaluator, "g = 10").unwrap();
+        assert_eq!(eval_in(&mut evaluator, "2g").unwrap(), Value::Integer(20));
+        assert_eq!(
+            eval_in(&mut evaluator, "f(c) = c + 1\nf(1)").unwrap(),
+            Value::Integer(2)
+        );
+    }
+
+    #[test]
+    fn test_default_assignment() {
+        assert_eq!(eval("x = 1; x ?= 5; x").unwrap(), Value::Integer(1));
+        assert_eq!(eval("y ?= 5; y").unwrap(), Value::Integer(5));
+    }
+
+    #[test]
+    fn test_default_assignment_skips_value_when_defined() {
+        assert_eq!(eval("x = 1; x ?= undefined; x").unwrap(), Value::Integer(1));
+        assert_eq!(
+            eval("pi ?= 3; pi").unwrap(),
+            Value::Number(std::f64::consts::PI)
+        );
+    }
+
+    #[test]
+    fn test_function_captures_definition_scope() {
+        assert_eq!(
+            eval("a = 1\nf() = a\na = 2\nf()").unwrap(),
+            Value::Integer(1)
+        );
+        assert_eq!(
+            eval("g(x) = x + 1\nf(x) = g(x)\ng(x) = x + 100\nf(1)").unwrap(),
+            Value::Integer(2)
+        );
+
+        // The caller's parameters and locals are not visible to the callee
+        assert!(matches!(
+            eval("f() = y\ng(y) = f()\ng(5)"),
+            Err(EvalError::UnknownVariable(..))
+        ));
+        assert!(matches!(
+            eval("f() = z\ng() = (z = 1; f())\ng()"),
+            Err(EvalError::UnknownVariable(..))
+        ));
+        // Names defined later at the top level still are
+        assert_eq!(
+            eval("f() = y\ng(x) = f() + x\ny = 10\ng(5)").unwrap(),
+            Value::Integer(15)
+        );
+    }
+
+    #[test]
+    fn test_closures_support_recursion() {
+        assert_eq!(
+            eval("fact(n) = n <= 1 ? 1 : n * fact(n - 1)\nfact(5)").unwrap(),
+            Value::Integer(120)
+        );
+        let source = "even(n) = n == 0 ? true : odd(n - 1)
+odd(n) = n == 0 ? false : even(n - 1)
+even(10)";
+        assert_eq!(eval(source).unwrap(), Value::Boolean(true));
+    }
+
+    #[test]
+    fn test_nested_definitions_capture_parameters() {
+        assert_eq!(
+            eval("adder(a) = (add(x) = x + a; add(10))\nadder(5)").unwrap(),
+            Value::Integer(15)
+        );
+        assert_eq!(
+            eval("a = 1\nf(a) = (g() = a; a = 2; g())\nf(7)").unwrap(),
+            Value::Integer(7)
+        );
+    }
+
+    fn eval_with_scope(model: ScopeModel, input: &str) -> Result<Value, EvalError> {
+        let mut evaluator = Evaluator::new();
+        evaluator.scope_model = model;
+        eval_in(&mut evaluator, input)
+    }
+
+    #[test]
+    fn test_scope_models_differ_on_later_assignment() {
+        assert_eq!(Evaluator::new().scope_model, ScopeModel::LexicalCapture);
+
+        let source = "a = 1; f(x) = x + a; a = 2; f(0)";
+        assert_eq!(
+            eval_with_scope(ScopeModel::LexicalCapture, source).unwrap(),
+            Value::Integer(1)
+        );
+        assert_eq!(
+            eval_with_scope(ScopeModel::DynamicClone, source).unwrap(),
+            Value::Integer(2)
+        );
+        assert_eq!(
+            eval_with_scope(ScopeModel::MutableChain, source).unwrap(),
+            Value::Integer(2)
+        );
+    }
+
+    #[test]
+    fn test_scope_models_on_returned_closures() {
+        // Only the call site is visible with dynamic scope, so `a` is gone
+        let source = "make(a) = x -> x + a\nadd5 = make(5)\nadd5(1)";
+        assert_eq!(
+            eval_with_scope(ScopeModel::LexicalCapture, source).unwrap(),
+            Value::Integer(6)
+        );
+        assert_eq!(
+            eval_with_scope(ScopeModel::MutableChain, source).unwrap(),
+            Value::Integer(6)
+        );
+        assert!(matches!(
+            eval_with_scope(ScopeModel::DynamicClone, source),
+            Err(EvalError::UnknownVariable(..))
+        ));
+
+        // A call's own bindings stay local under every model
+        for model in [
+            ScopeModel::DynamicClone,
+            ScopeModel::LexicalCapture,
+            ScopeModel::MutableChain,
+        ] {
+            let mut evaluator = Evaluator::new();
+            evaluator.scope_model = model;
+            eval_in(&mut evaluator, "y = 1\nf(x) = (y = x; y)\nf(9)").unwrap();
+            assert_eq!(evaluator.env.get("y".into()), Some(Value::Integer(1)));
+        }
+    }
+
+    #[test]
+    fn test_mutable_chain_function_values_can_be_formatted() {
+        // `f` is stored in the scope it captured, so its environment refers back to it
+        let mut evaluator = Evaluator::new();
+        evaluator.scope_model = ScopeModel::MutableChain;
+        let err = eval_in(&mut evaluator, "f(x) = x\nl = [f]\nl(1)").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unsupported expression: Attempted to call a non-function value: [<function f(x)>]"
+        );
+
+        let f = evaluator.env.get("f".into()).unwrap();
+        let debug = format!("{:?}", f);
+        assert!(
+            debug.starts_with("Function(FunctionValue { name: Some("),
+            "{}",
+            debug
+        );
+        assert!(debug.contains("Env { names: ["), "{}", debug);
+    }
+
+    #[test]
+    fn test_mutable_chain_closures_can_be_compared() {
+        // Each closure's scope holds the closure itself
+        let mut evaluator = Evaluator::new();
+        evaluator.scope_model = ScopeModel::MutableChain;
+        eval_in(
+            &mut evaluator,
+            "mk(n) = (g = y -> y + n; g)\na = mk(1)\nb = mk(1)",
+        )
+        .unwrap();
+
+        // Functions are equal only when they share a scope
+        assert_eq!(
+            eval_in(&mut evaluator, "count([a], b)").unwrap(),
+            Value::Integer(0)
+        );
+        assert_eq!(
+            eval_in(&mut evaluator, "count([a, b, a], a)").unwrap(),
+            Value::Integer(2)
+        );
+        assert_eq!(
+            eval_in(&mut evaluator, "b in [a]").unwrap(),
+            Value::Boolean(false)
+        );
+        assert_eq!(
+            eval_in(&mut evaluator, "count(unique([a, b, a]), b)").unwrap(),
+            Value::Integer(1)
+        );
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(
+            eval("a = 1.5\nb = [1, 2]\nformat(\"x = {}, y = {}\", a, b)").unwrap(),
+            Value::String("x = 1.5, y = [1, 2]".to_string())
+        );
+        assert_eq!(
+            eval("format(\"{{}} is {}\", \"empty\")").unwrap(),
+            Value::String("{} is empty".to_string())
+        );
+        assert_eq!(
+            eval("format(\"no placeholders\")").unwrap(),
+            Value::String("no placeholders".to_string())
+        );
+    }
+
+    #[test]
+    fn test_format_argument_mismatch() {
+        let err = eval("format(\"{} and {}\", 1)").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "format(): missing an argument for placeholder 2"
+        );
+
+        let err = eval("format(\"{}\", 1, 2)").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "format(): 1 placeholder(s) but 2 argument(s)"
+        );
+
+        assert!(matches!(
+            eval("format(\"{ }\", 1)").unwrap_err(),
+            EvalError::InvalidArgumentValue { .. }
+        ));
+        assert!(matches!(
+            eval("format(1)").unwrap_err(),
+            EvalError::InvalidArgument { .. }
+        ));
+    }
+
+    #[test]
+    fn test_recursive_function() {
+        assert_eq!(
+            eval("fact(n) = n <= 1 ? 1 : n * fact(n - 1)\nfact(5)").unwrap(),
+            Value::Integer(120)
+        );
+    }
+
+    #[test]
+    fn test_recursion_depth_limit() {
+        let mut evaluator = Evaluator::new();
+        evaluator.max_call_depth = 10;
+        eval_in(&mut evaluator, "s(n) = n == 0 ? 0 : n + s(n - 1)").unwrap();
+
+        assert_eq!(eval_in(&mut evaluator, "s(9)").unwrap(), Value::Integer(45));
+        let err = eval_in(&mut evaluator, "s(10)").unwrap_err();
+        assert!(matches!(err, EvalError::RecursionLimit(10)));
+        assert_eq!(
+            err.to_string(),
+            "Maximum recursion depth exceeded (10 nested calls)"
+        );
+        // Calls made by built-ins count too
+        assert!(matches!(
+            eval_in(&mut evaluator, "g(n) = max_by([n], x -> g(x + 1))\ng(0)").unwrap_err(),
+            EvalError::RecursionLimit(10)
+        ));
+        // The depth goes back down once a call returns
+        assert_eq!(eval_in(&mut evaluator, "s(9)").unwrap(), Value::Integer(45));
+    }
+
+    #[test]
+    fn test_default_recursion_limit_fits_an_8_mib_stack() {
+        let stopped_cleanly = std::thread::Builder::new()
+            .stack_size(8 << 20)
+            .spawn(|| {
+                matches!(
+                    eval("s(n) = n == 0 ? 0 : n + s(n - 1)\ns(250)"),
+                    Err(EvalError::RecursionLimit(DEFAULT_MAX_CALL_DEPTH))
+                )
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert!(stopped_cleanly);
+    }
+
+    #[test]
+    fn test_recursion_survives_rebinding_the_name() {
+        let source = "fact(n) = n <= 1 ? 1 : n * fact(n - 1)
+g = fact
+fact = 0
+g(5)";
+        assert_eq!(eval(source).unwrap(), Value::Integer(120));
+
+        let source = "make() = (fib(n) = n < 2 ? n : fib(n - 1) + fib(n - 2); fib)
+f = make()
+f(10)";
+        assert_eq!(eval(source).unwrap(), Value::Integer(55));
+    }
+
+    #[test]
+    fn test_lambda() {
+        assert_eq!(eval("f = x -> x * 2\nf(4)").unwrap(), Value::Integer(8));
+        assert_eq!(
+            eval("n = 3\nadd_n = x -> x + n\nn = 100\nadd_n(1)").unwrap(),
+            Value::Integer(4)
+        );
+    }
+
+    #[test]
+    fn test_max_by_min_by() {
+        assert_eq!(
+            eval("max_by([1, -3, 2], x -> abs(x))").unwrap(),
+            Value::Integer(-3)
+        );
+        assert_eq!(
+            eval("min_by([1, -3, 2], x -> abs(x))").unwrap(),
+            Value::Integer(1)
+        );
+        assert_eq!(
+            eval("max_by([2, -2, 1], x -> x^2)").unwrap(),
+            Value::Integer(2)
+        );
+        assert_eq!(eval("min_by([4, 9], sqrt)").unwrap(), Value::Integer(4));
+        assert_eq!(
+            eval("key(s) = count(s, 1)\nmax_by([[1], [1, 1], [2]], key)").unwrap(),
+            integers(&[1, 1])
+        );
+    }
+
+    #[test]
+    fn test_try_map() {
+        let result = eval("try_map([1, 0, 2], x -> 10 / x)").unwrap();
+        let Value::List(parts) = result else {
+            panic!("expected a list, got {:?}", result);
+        };
+        assert_eq!(parts[0], integers(&[10, 5]));
+        assert_eq!(
+            parts[1],
+            Value::List(vec![Value::List(vec![
+                Value::Integer(1),
+                Value::String(EvalError::DivisionByZero(10.0).to_string()),
+            ])])
+        );
+
+        assert_eq!(
+            eval("try_map([4, 9], sqrt)").unwrap(),
+            Value::List(vec![numbers(&[2.0, 3.0]), Value::List(vec![])])
+        );
+        assert!(matches!(
+            eval("try_map([1], 2)").unwrap_err(),
+            EvalError::InvalidArgument {
+                expected: "function",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_keys_and_values() {
+        let source = "m = [[\"z\", 1], [\"a\", 2], [\"m\", 3]]\n";
+        let strings =
+            |xs: &[&str]| Value::List(xs.iter().map(|&s| Value::String(s.into())).collect());
+
+        // Entries come back in the order they were written, on every run
+        for _ in 0..3 {
+            assert_eq!(
+                eval(&format!("{}keys(m)", source)).unwrap(),
+                strings(&["z", "a", "m"])
+            );
+            assert_eq!(
+                eval(&format!("{}values(m)", source)).unwrap(),
+                integers(&[1, 2, 3])
+            );
+        }
+        assert_eq!(eval("keys([])").unwrap(), Value::List(vec![]));
+
+        let err = eval("values([[1, 2], 3])").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "values(): expected a list of [key, value] pairs, found 3"
+        );
+    }
+
+    #[test]
+    fn test_max_by_errors() {
+        let err = eval("max_by([], x -> x)").unwrap_err();
+        assert_eq!(err.to_string(), "max_by(): expected a non-empty list");
+
+        assert!(matches!(
+            eval("min_by([1], 2)").unwrap_err(),
+            EvalError::InvalidArgument {
+                expected: "function",
+                ..
+            }
+        ));
+        assert!(matches!(
+            eval("max_by([1], x -> [x])").unwrap_err(),
+            EvalError::InvalidArgument {
+                expected: "number",
+                found: "list",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_if_expression() {
+        assert_eq!(
+            eval("x = 4\nif x != 0 then 1/x else 0").unwrap(),
+            Value::Rational { num: 1, den: 4 }
+        );
+        assert_eq!(
+            eval("x = 0\nif x != 0 then 1/x else 0").unwrap(),
+            Value::Integer(0)
+        );
+        assert_eq!(
+            eval("sign(x) = if x < 0 then -1 else if x > 0 then 1 else 0\nsign(-5)").unwrap(),
+            Value::Integer(-1)
+        );
+    }
+
+    #[test]
+    fn test_if_expression_requires_boolean() {
+        let err = eval("if 1 then 2 else 3").unwrap_err();
+        assert_eq!(err.to_string(), "Condition must be a boolean, found number");
+    }
+
+    #[test]
+    fn test_nested_ternary() {
+        let grade = "grade(s) = s >= 90 ? 1 : s >= 80 ? 2 : s >= 70 ? 3 : 4";
+        assert_eq!(
+            eval(&format!("{}\ngrade(95)", grade)).unwrap(),
+            Value::Integer(1)
+        );
+        assert_eq!(
+            eval(&format!("{}\ngrade(85)", grade)).unwrap(),
+            Value::Integer(2)
+        );
+        assert_eq!(
+            eval(&format!("{}\ngrade(10)", grade)).unwrap(),
+            Value::Integer(4)
+        );
+        assert_eq!(eval("x = -3\nx > 0 ? x : -x").unwrap(), Value::Integer(3));
+    }
+
+    #[test]
+    fn test_index() {
+        assert_eq!(
+            eval("xs = [10, 20, 30]\nxs[0]").unwrap(),
+            Value::Integer(10)
+        );
+        assert_eq!(
+            eval("xs = [10, 20, 30]\nxs[2]").unwrap(),
+            Value::Integer(30)
+        );
+        assert_eq!(
+            eval("m = [[1, 2], [3, 4]]\nm[1][0]").unwrap(),
+            Value::Integer(3)
+        );
+        assert_eq!(
+            eval("xs = [1, 2]\nxs[1 - 1] + 1").unwrap(),
+            Value::Integer(2)
+        );
+    }
+
+    #[test]
+    fn test_index_errors() {
+        let err = eval("[10, 20, 30][3]").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Index 3 is out of bounds for a list of length 3"
+        );
+        assert!(matches!(
+            eval("[10, 20, 30][1.5]").unwrap_err(),
+            EvalError::ExpectedInteger(n) if n == 1.5
+        ));
+        assert!(matches!(
+            eval("[10, 20, 30][\"a\"]").unwrap_err(),
+            EvalError::InvalidIndex("string")
+        ));
+        assert!(matches!(
+            eval("x = 5\nx[0]").unwrap_err(),
+            EvalError::NotIndexable("number")
+        ));
+    }
+
+    #[test]
+    fn test_flatten() {
+        let list = |xs: &[i64]| Value::List(xs.iter().map(|&x| Value::Integer(x)).collect());
+
+        assert_eq!(
+            eval("flatten([[1, 2], [3, [4, 5]]])").unwrap(),
+            list(&[1, 2, 3, 4, 5])
+        );
+        assert_eq!(
+            eval("flatten([[1, 2], [3, [4, 5]]], 1)").unwrap(),
+            Value::List(vec![
+                Value::Integer(1),
+                Value::Integer(2),
+                Value::Integer(3),
+                list(&[4, 5]),
+            ])
+        );
+        assert_eq!(eval("flatten([1, 2, 3])").unwrap(), list(&[1, 2, 3]));
+        assert_eq!(
+            eval("flatten([[1], [2]], 0)").unwrap(),
+            eval("[[1], [2]]").unwrap()
+        );
+        assert_eq!(eval("flatten([[], [[]]])").unwrap(), Value::List(vec![]));
+    }
+
+    #[test]
+    fn test_flatten_errors() {
+        assert!(matches!(
+            eval("flatten(1)").unwrap_err(),
+            EvalError::InvalidArgument {
+                expected: "list",
+                ..
+            }
+        ));
+        assert!(matches!(
+            eval("flatten([[1]], -1)").unwrap_err(),
+            EvalError::InvalidArgumentValue { .. }
+        ));
+        assert!(matches!(
+            eval("flatten([[1]], 1.5)").unwrap_err(),
+            EvalError::InvalidArgumentValue { .. }
+        ));
+    }
+
+    #[test]
+    fn test_sum_and_product_keep_fractions_exact() {
+        assert_eq!(eval("sum([1/3, 1/6])").unwrap(), eval("1/3 + 1/6").unwrap());
+        assert_eq!(
+            eval("sum([1/3, 1/6])").unwrap(),
+            Value::Rational { num: 1, den: 2 }
+        );
+        assert_eq!(
+            eval("product([2/3, 3/4], 1/5)").unwrap(),
+            Value::Rational { num: 1, den: 10 }
+        );
+    }
+
+    #[test]
+    fn test_sum_and_product() {
+        assert_eq!(eval("sum([1, 2, 3])").unwrap(), Value::Integer(6));
+        assert_eq!(eval("sum([1, 2, 3], 10)").unwrap(), Value::Integer(16));
+        assert_eq!(eval("product([1, 2, 3])").unwrap(), Value::Integer(6));
+        assert_eq!(eval("product([1, 2, 3], 2)").unwrap(), Value::Integer(12));
+        assert_eq!(eval("sum(1..5)").unwrap(), Value::Integer(10));
+        assert_eq!(eval("sum([1, 2.5])").unwrap(), Value::Number(3.5));
+
+        // An empty list gives the starting value
+        assert_eq!(eval("sum([])").unwrap(), Value::Integer(0));
+        assert_eq!(eval("product([])").unwrap(), Value::Integer(1));
+        assert_eq!(eval("sum([], 5)").unwrap(), Value::Integer(5));
+
+        let err = eval("product([1, 2, true, 4])").unwrap_err();
+        assert!(matches!(
+            err,
+            EvalError::InvalidElement {
+                index: 2,
+                expected: "number",
+                found: "boolean",
+                ..
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "product() expected a list of numbers, found boolean at index 2"
+        );
+        assert!(matches!(
+            eval("sum([1, \"a\"])").unwrap_err(),
+            EvalError::InvalidElement { index: 1, .. }
+        ));
+        // Exact fractions are numbers too
+        assert_eq!(eval("sum([1/2, 1/2])").unwrap(), Value::Integer(1));
+        assert!(matches!(
+            eval("product()").unwrap_err(),
+            EvalError::ArgumentCount { found: 0, .. }
+        ));
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        assert_eq!(eval("x = 5\nx += 1\nx").unwrap(), Value::Integer(6));
+        assert_eq!(eval("x = 5\nx -= 2").unwrap(), Value::Integer(3));
+        assert_eq!(eval("x = 5\nx *= 1 + 1\nx").unwrap(), Value::Integer(10));
+        assert_eq!(
+            eval("x = 5\nx /= 2\nx").unwrap(),
+            Value::Rational { num: 5, den: 2 }
+        );
+        assert_eq!(
+            eval("s = \"ab\"\ns += \"c\"").unwrap(),
+            Value::String("abc".into())
+        );
+    }
+
+    #[test]
+    fn test_compound_assignment_errors() {
+        assert!(matches!(
+            eval("y += 1").unwrap_err(),
+            EvalError::UnknownVariable(name, _) if name == "y"
+        ));
+        assert!(matches!(
+            eval("f(x) = x\nf += 1").unwrap_err(),
+            EvalError::InvalidOperands { .. }
+        ));
+        assert!(matches!(
+            eval("x = 1\nx /= 0").unwrap_err(),
+            EvalError::DivisionByZero(_)
+        ));
+    }
+
+    #[test]
+    fn test_exactdiv() {
+        assert_eq!(eval("exactdiv(10, 2)").unwrap(), Value::Number(5.0));
+        assert_eq!(eval("exactdiv(-9, 3)").unwrap(), Value::Number(-3.0));
+        assert_eq!(eval("exactdiv(1.5, 0.5)").unwrap(), Value::Number(3.0));
+
+        let err = eval("exactdiv(10, 3)").unwrap_err();
+        assert!(matches!(err, EvalError::NotDivisible(a, b) if a == 10.0 && b == 3.0));
+        assert_eq!(err.to_string(), "10 is not exactly divisible by 3");
+
+        assert!(matches!(
+            eval("exactdiv(1, 0)").unwrap_err(),
+            EvalError::DivisionByZero(n) if n == 1.0
+        ));
+    }
+
+    #[test]
+    fn test_rational_division() {
+        assert_eq!(eval("1/3").unwrap(), Value::Rational { num: 1, den: 3 });
+        assert_eq!(eval("6/4").unwrap(), Value::Rational { num: 3, den: 2 });
+        assert_eq!(eval("3/-6").unwrap(), Value::Rational { num: -1, den: 2 });
+        assert_eq!(eval("-(1/3)").unwrap(), Value::Rational { num: -1, den: 3 });
+        assert_eq!(eval("1/3").unwrap().to_string(), "1/3");
+    }
+
+    #[test]
+    fn test_rational_arithmetic() {
+        assert_eq!(
+            eval("1/3 + 1/6").unwrap(),
+            Value::Rational { num: 1, den: 2 }
+        );
+        assert_eq!(eval("1/3 + 2/3").unwrap(), Value::Integer(1));
+        assert_eq!(eval("1 - 1/4").unwrap(), Value::Rational { num: 3, den: 4 });
+        assert_eq!(
+            eval("2/3 * 3/4").unwrap(),
+            Value::Rational { num: 1, den: 2 }
+        );
+        assert_eq!(
+            eval("(1/3) / (2/3)").unwrap(),
+            Value::Rational { num: 1, den: 2 }
+        );
+        assert_eq!(
+            eval("(2/3) ^ 2").unwrap(),
+            Value::Rational { num: 4, den: 9 }
+        );
+        assert_eq!(
+            eval("(2/3) ^ -1").unwrap(),
+            Value::Rational { num: 3, den: 2 }
+        );
+
+        // Anything that is not an integer or a rational makes the result a float
+        assert_eq!(eval("1/4 + 0.5").unwrap(), Value::Number(0.75));
+        assert_eq!(eval("(1/4) ^ 0.5").unwrap(), Value::Number(0.5));
+        assert_eq!(eval("(1/2) == 0.5").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("1/3 < 1/2").unwrap(), Value::Boolean(true));
+    }
+
+    #[test]
+    fn test_signed_exponent_before_implicit_product() {
+        assert_eq!(eval("x = 2\n3^2x").unwrap(), Value::Integer(18));
+        assert!((number(eval("x = 2\n3^-2x").unwrap()) - 2.0 / 9.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_rational_equality_matches_numbers() {
+        assert_eq!(eval("1/2 == 0.5").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("0.5 != 1/2").unwrap(), Value::Boolean(false));
+        assert_eq!(eval("[1/2] == [0.5]").unwrap(), Value::Boolean(true));
+        assert_eq!(
+            eval("[[0.5], 1] == [[1/2], 1]").unwrap(),
+            Value::Boolean(true)
+        );
+        assert_eq!(eval("[1/3] == [0.5]").unwrap(), Value::Boolean(false));
+
+        assert_eq!(eval("1/2 in [0.5]").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("0.5 in [1/2]").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("[0.5] in [[1/2]]").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("0.5 not in [1/2]").unwrap(), Value::Boolean(false));
+
+        assert_eq!(eval("assert_eq(1/2, 0.5)").unwrap(), Value::Unit);
+        assert_eq!(eval("assert_eq([0.5], [1/2])").unwrap(), Value::Unit);
+        assert!(matches!(
+            eval("assert_eq(1/3, 0.5)").unwrap_err(),
+            EvalError::AssertionFailed(..)
+        ));
+
+        assert_eq!(
+            eval("count([1/2, 0.5, 2/4], 0.5)").unwrap(),
+            Value::Integer(3)
+        );
+        assert_eq!(
+            eval("count([0.5, [1/2]], [0.5])").unwrap(),
+            Value::Integer(1)
+        );
+        assert_eq!(
+            eval("unique([1/2, 0.5, 1/3])").unwrap(),
+            Value::List(vec![
+                Value::Rational { num: 1, den: 2 },
+                Value::Rational { num: 1, den: 3 },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_integer_arithmetic() {
+        // Literals without a dot or exponent are integers and stay exact
+        assert_eq!(eval("7 + 3").unwrap(), Value::Integer(10));
+        assert_eq!(eval("7 - 10").unwrap(), Value::Integer(-3));
+        assert_eq!(eval("6 * 7").unwrap(), Value::Integer(42));
+        assert_eq!(eval("-7 % 3").unwrap(), Value::Integer(2));
+        assert_eq!(eval("2^62").unwrap(), Value::Integer(1 << 62));
+        // `/` stays exact: even division gives an integer, uneven a rational
+        assert_eq!(eval("10 / 2").unwrap(), Value::Integer(5));
+        assert_eq!(eval("10 / 3").unwrap(), Value::Rational { num: 10, den: 3 });
+        // Overflow falls back to a float instead of wrapping
+        assert_eq!(
+            eval("9223372036854775807 + 1").unwrap(),
+            Value::Number(9223372036854775808.0)
+        );
+    }
+
+    #[test]
+    fn test_integer_and_float_mixing() {
+        // A float operand promotes the integer, so the result is a float
+        assert_eq!(eval("2.5 + 1").unwrap(), Value::Number(3.5));
+        assert_eq!(eval("2 * 1.5").unwrap(), Value::Number(3.0));
+        assert_eq!(eval("1e2 - 1").unwrap(), Value::Number(99.0));
+        assert_eq!(
+            eval("10 / 3 + 0.5").unwrap(),
+            Value::Number(10.0 / 3.0 + 0.5)
+        );
+        // Both kinds still compare by value
+        assert_eq!(eval("1 == 1.0").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("[1, 2] == [1.0, 2.0]").unwrap(), Value::Boolean(true));
+        assert_eq!(eval("2 in [1.0, 2.0]").unwrap(), Value::Boolean(true));
+    }
+
+    #[test]
+    fn test_integer_display() {
+        assert_eq!(eval("7 + 3").unwrap().to_string(), "10");
+        assert_eq!(eval("-4").unwrap().to_string(), "-4");
+        // Integers print every digit, where a float that large switches to an exponent
+        assert_eq!(eval("10^17").unwrap().to_string(), "100000000000000000");
+        assert_eq!(eval("10.0^17").unwrap().to_string(), "1e17");
+        assert_eq!(eval("2.5 + 1").unwrap().to_string(), "3.5");
+        assert_eq!(eval("10 / 3").unwrap().to_string(), "10/3");
+    }
+
+    #[test]
+    fn test_to_float() {
+        let Value::Number(n) = eval("to_float(1/3)").unwrap() else {
+            panic!("expected a number");
+        };
+        assert!((n - 0.333).abs() < 0.001);
+        assert_eq!(eval("to_float(2)").unwrap(), Value::Number(2.0));
+        assert_eq!(eval("sqrt(1/4)").unwrap(), Value::Number(0.5));
+        assert_eq!(eval("[10, 20][2/2]").unwrap(), Value::Integer(20));
+        assert!(matches!(
+            eval("[10, 20][1/2]").unwrap_err(),
+            EvalError::ExpectedInteger(n) if n == 0.5
+        ));
+    }
+
+    #[test]
+    fn test_display_numbers() {
+        let display = |source: &str| eval(source).unwrap().to_string();
+        assert_eq!(display("4.0"), "4");
+        assert_eq!(display("2.5"), "2.5");
+        assert_eq!(display("-3"), "-3");
+        assert_eq!(display("-0"), "0");
+        assert_eq!(display("10 ^ 21"), "1e21");
+        assert_eq!(display("-1.5e20"), "-1.5e20");
+        assert_eq!(display("0.00000025"), "2.5e-7");
+        assert_eq!(display("123456789012345"), "123456789012345");
+        assert_eq!(display("0.001"), "0.001");
+        assert_eq!(display("inf"), "inf");
+        assert_eq!(display("-inf"), "-inf");
+        assert_eq!(display("inf - inf"), "NaN");
+    }
+
+    #[test]
+    fn test_display_other_values() {
+        let display = |source: &str| eval(source).unwrap().to_string();
+        assert_eq!(display("\"hi\""), "hi");
+        assert_eq!(display("[1, [true, \"a\"]]"), "[1, [true, a]]");
+        assert_eq!(display("f(x, y) = x + y\nf"), "<function f(x, y)>");
+        assert_eq!(display("g() = 1\ng"), "<function g()>");
+        assert_eq!(display("x -> x * 2"), "<function (x)>");
+        assert_eq!(display("sqrt"), "<function sqrt>");
+        assert_eq!(display("f(x) = 1"), "()");
+    }
+
+    #[test]
+    fn test_check_definitions_warns_about_undefined_names() {
+        let mut evaluator = Evaluator::new();
+        evaluator.check_definitions = true;
+        eval_in(&mut evaluator, "f(x) = y").unwrap();
+        assert_eq!(
+            evaluator.warnings,
+            vec!["'f' uses 'y', which is not defined yet".to_string()]
+        );
+
+        // Still only a warning: defining y later makes the call work
+        assert_eq!(
+            eval_in(&mut evaluator, "y = 2\nf(1)").unwrap(),
+            Value::Integer(2)
+        );
+    }
+
+    #[test]
+    fn test_check_definitions_ignores_known_names() {
+        let mut evaluator = Evaluator::new();
+        evaluator.check_definitions = true;
+        eval_in(
+            &mut evaluator,
+            "a = 1\n\
+             fact(n) = n <= 1 ? 1 : n * fact(n - 1)\n\
+             g(x) = (t = x * pi; sqrt(t) + a)\n\
+             h(xs) = max_by(xs, v -> v * a)",
+        )
+        .unwrap();
+        assert!(evaluator.warnings.is_empty(), "{:?}", evaluator.warnings);
+
+        eval_in(&mut evaluator, "k(x) = x + b + b + c2").unwrap();
+        assert_eq!(evaluator.warnings.len(), 2);
+        assert!(evaluator.warnings[0].contains("'b'"));
+        assert!(evaluator.warnings[1].contains("'c2'"));
+    }
+
+    #[test]
+    fn test_check_definitions_warnings_from_calls_reach_the_caller() {
+        let mut evaluator = Evaluator::new();
+        evaluator.check_definitions = true;
+        eval_in(&mut evaluator, "make() = (g(x) = x + later; g)").unwrap();
+        assert_eq!(evaluator.warnings.len(), 1);
+
+        // The definition of `g` only runs inside the call
+        evaluator.warnings.clear();
+        eval_in(&mut evaluator, "h = make()").unwrap();
+        assert_eq!(
+            evaluator.warnings,
+            vec!["'g' uses 'later', which is not defined yet".to_string()]
+        );
+
+        // Also through a built-in calling the function, and when the call fails
+        evaluator.warnings.clear();
+        let err = eval_in(&mut evaluator, "max_by([1], v -> (k() = missing; k()))");
+        assert!(matches!(err, Err(EvalError::UnknownVariable(..))));
+        assert_eq!(evaluator.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_check_definitions_is_off_by_default() {
+        let mut evaluator = Evaluator::new();
+        eval_in(&mut evaluator, "f(x) = y").unwrap();
+        assert!(evaluator.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_recursive_functions() {
+        assert_eq!(
+            eval("fib(n) = n < 2 ? n : fib(n - 1) + fib(n - 2)\nfib(15)").unwrap(),
+            Value::Integer(610)
+        );
+        assert_eq!(
+            eval("sum_to(n) = n == 0 ? 0 : n + sum_to(n - 1)\nsum_to(20)").unwrap(),
+            Value::Integer(210)
+        );
+        // Each call has its own parameters, even while an outer call is still running
+        assert_eq!(
+            eval("f(n) = n == 0 ? 0 : (x = n; f(n - 1); x)\nf(5)").unwrap(),
+            Value::Integer(5)
+        );
+    }
+
+    #[test]
+    fn test_env_frame_lookup() {
+        let mut global = Env::new();
+        global.set("a".into(), Value::Number(1.0));
+
+        let mut frame = Env::frame(vec![global.clone()]);
+        assert_eq!(frame.get("a".into()), Some(Value::Number(1.0)));
+        assert!(frame.contains("pi".into()));
+
+        // Setting in a frame shadows the parent without changing it
+        frame.set("a".into(), Value::Number(2.0));
+        assert_eq!(frame.get("a".into()), Some(Value::Number(2.0)));
+        assert_eq!(global.get("a".into()), Some(Value::Number(1.0)));
+
+        // Bindings added to the parent later are visible through the frame
+        global.set("b".into(), Value::Number(3.0));
+        assert_eq!(frame.get("b".into()), Some(Value::Number(3.0)));
+    }
+
+    #[test]
+    fn test_env_snapshot_is_independent() {
+        let mut global = Env::new();
+        global.set_const("k".into(), Value::Number(1.0));
+        global.set("a".into(), Value::Number(1.0));
+
+        let snapshot = global.snapshot();
+        global.set("a".into(), Value::Number(2.0));
+        assert_eq!(snapshot.get("a".into()), Some(Value::Number(1.0)));
+        assert!(snapshot.is_const("k".into()));
+
+        // The nearest binding decides whether a name is constant
+        let mut frame = Env::frame(vec![global.clone()]);
+        assert!(frame.is_const("k".into()));
+        frame.set("k".into(), Value::Number(5.0));
+        assert!(!frame.is_const("k".into()));
+    }
+
+    #[test]
+    fn test_lookups_across_parses() {
+        // Each statement is parsed on its own, so names are interned separately
+        let mut evaluator = Evaluator::new();
+        eval_in(&mut evaluator, "rate = 3").unwrap();
+        eval_in(&mut evaluator, "scale(x) = x * rate").unwrap();
+        assert_eq!(
+            eval_in(&mut evaluator, "scale(2) + rate").unwrap(),
+            Value::Integer(9)
+        );
+        assert_eq!(evaluator.env.get("rate".into()), Some(Value::Integer(3)));
+        assert!(matches!(
+            eval_in(&mut evaluator, "rat"),
+            Err(EvalError::UnknownVariable(name, Some(suggestion)))
+                if name == "rat" && suggestion == "rate"
+        ));
+    }
+}
@@ -1,14 +1,155 @@
-use crate::parser::ast::Expr;
+use std::{fmt, rc::Rc};
 
-#[derive(Clone, Debug)]
+use crate::{
+    interpreter::{builtins::Builtin, env::Env, error::EvalError},
+    parser::{ast::Expr, symbol::Symbol},
+};
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum Value {
     Number(f64),
+    // Integer literals and exact arithmetic on them; mixed with a float, a float
+    Integer(i64),
+    // Always in lowest terms with den > 1; whole results are integers
+    Rational { num: i64, den: i64 },
+    String(String),
+    Boolean(bool),
+    List(Vec<Value>),
     Function(FunctionValue),
+    Builtin(Builtin),
     Unit,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct FunctionValue {
-    pub params: Vec<String>,
-    pub body: Expr,
-}
\ No newline at end of file
+    // None for lambdas
+    pub name: Option<Symbol>,
+    pub params: Vec<Symbol>,
+    // Shared, so passing a function around does not copy its body
+    pub body: Rc<Expr>,
+    // Scope at the point of definition
+    pub env: Env,
+}
+
+impl Value {
+    // Reduces num/den, falling back to a float when it does not fit in i64
+    pub fn rational(num: i128, den: i128) -> Value {
+        let sign = if den < 0 { -1 } else { 1 };
+        let divisor = gcd(num, den).max(1);
+        let (num, den) = (sign * num / divisor, sign * den / divisor);
+
+        match (i64::try_from(num), i64::try_from(den)) {
+            (Ok(num), _) if den == 1 => Value::Integer(num),
+            _ if den == 1 => Value::Number(num as f64),
+            (Ok(num), Ok(den)) => Value::Rational { num, den },
+            _ => Value::Number(num as f64 / den as f64),
+        }
+    }
+
+    // Integers and rationals become the nearest float, everything else is unchanged
+    pub fn into_float(self) -> Value {
+        match self {
+            Value::Integer(n) => Value::Number(n as f64),
+            Value::Rational { num, den } => Value::Number(num as f64 / den as f64),
+            other => other,
+        }
+    }
+
+    // Equality as `==` sees it: numbers of different kinds are equal when their
+    // values are, also inside lists, which the derived PartialEq does not see
+    pub fn equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Integer(a), Value::Integer(b)) => a == b,
+            (
+                Value::Number(_) | Value::Integer(_) | Value::Rational { .. },
+                Value::Number(_) | Value::Integer(_) | Value::Rational { .. },
+            ) if std::mem::discriminant(self) != std::mem::discriminant(other) => {
+                self.clone().into_float() == other.clone().into_float()
+            }
+            (Value::List(xs), Value::List(ys)) => {
+                xs.len() == ys.len() && xs.iter().zip(ys).all(|(x, y)| x.equals(y))
+            }
+            _ => self == other,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(_) | Value::Integer(_) | Value::Rational { .. } => "number",
+            Value::String(_) => "string",
+            Value::Boolean(_) => "boolean",
+            Value::List(_) => "list",
+            Value::Function(_) | Value::Builtin(_) => "function",
+            Value::Unit => "unit",
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{}", format_number(*n)),
+            Value::Integer(n) => write!(f, "{}", n),
+            Value::Rational { num, den } => write!(f, "{}/{}", num, den),
+            Value::String(s) => write!(f, "{}", s),
+            Value::Boolean(b) => write!(f, "{}", b),
+            Value::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Value::Function(function) => write!(
+                f,
+                "<function {}({})>",
+                function.name.map_or("", Symbol::as_str),
+                function
+                    .params
+                    .iter()
+                    .map(|param| param.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Value::Builtin(builtin) => write!(f, "<function {}>", builtin.name),
+            Value::Unit => write!(f, "()"),
+        }
+    }
+}
+
+// Scientific notation once plain digits would get unwieldy: 1e21, 2.5e-7
+fn format_number(n: f64) -> String {
+    if n == 0.0 {
+        // Also turns -0 into 0
+        return "0".to_string();
+    }
+    if n.is_finite() && !(1e-6..1e16).contains(&n.abs()) {
+        return format!("{:e}", n);
+    }
+    n.to_string()
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+// Negative indices count from the end: xs[-1] is the last element
+pub fn to_index(i: f64, len: usize) -> Result<usize, EvalError> {
+    if i.fract() != 0.0 {
+        return Err(EvalError::ExpectedInteger(i));
+    }
+
+    let position = if i < 0.0 { i + len as f64 } else { i };
+    if position < 0.0 || position >= len as f64 {
+        return Err(EvalError::IndexOutOfBounds { index: i, len });
+    }
+
+    Ok(position as usize)
+}
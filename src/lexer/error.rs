@@ -5,6 +5,57 @@ pub enum LexerError {
     #[error("Unexpected character '{0}' at line {1}, column {2}")]
     UnexpectedCharacter(char, usize, usize),
 
-    #[error("Invalid number format '{0}' at line {1}, column {2}")]
-    InvalidNumberFormat(String, usize, usize),
-}
\ No newline at end of file
+    #[error("Unterminated string starting at line {0}, column {1}")]
+    UnterminatedString(usize, usize),
+
+    #[error("Unterminated `identifier` starting at line {0}, column {1}")]
+    UnterminatedIdentifier(usize, usize),
+
+    #[error("Empty `identifier` at line {0}, column {1}")]
+    EmptyIdentifier(usize, usize),
+
+    #[error("Invalid escape sequence '\\{0}' at line {1}, column {2}")]
+    InvalidEscape(char, usize, usize),
+
+    #[error("Unknown operator '{found}' at line {line}, column {col} (did you mean '{expected}'?)")]
+    MistypedOperator {
+        found: &'static str,
+        expected: &'static str,
+        line: usize,
+        col: usize,
+    },
+
+    #[error("Invalid number format '{text}' at line {line}, column {col}")]
+    InvalidNumberFormat {
+        text: String,
+        line: usize,
+        col: usize,
+        pos: usize,
+        len: usize,
+    },
+}
+
+impl LexerError {
+    pub fn location(&self) -> (usize, usize) {
+        match self {
+            LexerError::UnexpectedCharacter(_, line, col) => (*line, *col),
+            LexerError::UnterminatedString(line, col) => (*line, *col),
+            LexerError::UnterminatedIdentifier(line, col) => (*line, *col),
+            LexerError::EmptyIdentifier(line, col) => (*line, *col),
+            LexerError::InvalidEscape(_, line, col) => (*line, *col),
+            LexerError::MistypedOperator { line, col, .. } => (*line, *col),
+            LexerError::InvalidNumberFormat { line, col, .. } => (*line, *col),
+        }
+    }
+
+    pub fn span_len(&self) -> usize {
+        match self {
+            LexerError::UnexpectedCharacter(..)
+            | LexerError::UnterminatedString(..)
+            | LexerError::UnterminatedIdentifier(..) => 1,
+            LexerError::InvalidEscape(..) | LexerError::EmptyIdentifier(..) => 2,
+            LexerError::MistypedOperator { found, .. } => found.len(),
+            LexerError::InvalidNumberFormat { len, .. } => *len,
+        }
+    }
+}
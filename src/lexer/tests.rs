@@ -1,6 +1,10 @@
 #[cfg(test)]
 mod lexer_tests {
-    use crate::lexer::{error::LexerError, token::{SpannedToken, Token}, tokenizer::Lexer};
+    use crate::lexer::{
+        error::LexerError,
+        token::{SpannedToken, Token},
+        tokenizer::{DoubleSlashMode, Lexer},
+    };
 
     fn filter_tokens(tokens: Vec<SpannedToken>) -> Vec<Token> {
         tokens
@@ -10,6 +14,15 @@ mod lexer_tests {
             .collect()
     }
 
+    // Like filter_tokens, but keeps the line and column of each token
+    fn positioned_tokens(tokens: Vec<SpannedToken>) -> Vec<(Token, usize, usize)> {
+        tokens
+            .into_iter()
+            .filter(|spanned| spanned.value != Token::Whitespace)
+            .map(|spanned| (spanned.value, spanned.span.line, spanned.span.col))
+            .collect()
+    }
+
     #[test]
     fn test_numbers() {
         let mut lexer = Lexer::new("12 3.45 6.7 .89 1.23e4 5.6E-2");
@@ -17,7 +30,7 @@ mod lexer_tests {
         assert_eq!(
             filter_tokens(tokens),
             vec![
-                Token::Number(12.0),
+                Token::Integer(12),
                 Token::Number(3.45),
                 Token::Number(6.7),
                 Token::Number(0.89),
@@ -28,6 +41,97 @@ mod lexer_tests {
         );
     }
 
+    #[test]
+    fn test_integer_literals() {
+        // A dot or an exponent makes a float, as does a literal too big for an i64
+        let mut lexer = Lexer::new("12 12.0 12e0 0x10 99999999999999999999");
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(
+            filter_tokens(tokens),
+            vec![
+                Token::Integer(12),
+                Token::Number(12.0),
+                Token::Number(12.0),
+                Token::Integer(16),
+                Token::Number(1e20),
+                Token::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_digit_separators() {
+        let mut lexer = Lexer::new("1_000_000 1.234_567 1_0e1_0");
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(
+            filter_tokens(tokens),
+            vec![
+                Token::Integer(1000000),
+                Token::Number(1.234567),
+                Token::Number(10e10),
+                Token::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_misplaced_digit_separators() {
+        for input in ["1__0", "1_.0", "1._0", "1_", "1_e5", "1e_5"] {
+            let mut lexer = Lexer::new(input);
+            let errors = lexer.tokenize().unwrap_err();
+            assert!(
+                matches!(&errors[0], LexerError::InvalidNumberFormat { text, .. } if text == input),
+                "{:?}",
+                errors
+            );
+        }
+    }
+
+    #[test]
+    fn test_exponent_without_digits() {
+        for input in ["1e", "1e+", "2.5E-"] {
+            let mut lexer = Lexer::new(input);
+            let errors = lexer.tokenize().unwrap_err();
+            assert!(
+                matches!(&errors[0], LexerError::InvalidNumberFormat { text, .. } if text == input),
+                "{:?}",
+                errors
+            );
+        }
+    }
+
+    #[test]
+    fn test_radix_numbers() {
+        let mut lexer = Lexer::new("0x1F 0XfF 0b1010 0o17 0 0.5");
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(
+            filter_tokens(tokens),
+            vec![
+                Token::Integer(31),
+                Token::Integer(255),
+                Token::Integer(10),
+                Token::Integer(15),
+                Token::Integer(0),
+                Token::Number(0.5),
+                Token::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_radix_numbers() {
+        let cases = [("0xZ", "0xZ"), ("0b102", "0b102"), ("0x", "0x"), ("0o8 + 1", "0o8")];
+        for (input, text) in cases {
+            let mut lexer = Lexer::new(input);
+            let errors = lexer.tokenize().unwrap_err();
+            assert!(
+                matches!(&errors[0], LexerError::InvalidNumberFormat { text: t, .. } if t == text),
+                "{:?}",
+                errors
+            );
+        }
+    }
+
     #[test]
     fn test_identifiers() {
         let mut lexer = Lexer::new("x foo bar1 _baz");
@@ -112,14 +216,34 @@ mod lexer_tests {
         let err = lexer.tokenize().unwrap_err();
         assert_eq!(err.len(), 1);
         match &err[0] {
-            LexerError::InvalidNumberFormat(s, _, _) => {
-                println!("Invalid number format: {}", s);
-                assert_eq!(s, "12.3.4");
+            LexerError::InvalidNumberFormat { text, .. } => {
+                assert_eq!(text, "12.3.4");
             }
             _ => panic!("Expected InvalidNumberFormat"),
         }
     }
 
+    #[test]
+    fn test_letter_after_exponent() {
+        // The whole scientific number is read first; a trailing letter starts an identifier
+        let mut lexer = Lexer::new("1.5e3i");
+        assert_eq!(
+            filter_tokens(lexer.tokenize().unwrap()),
+            vec![
+                Token::Number(1500.0),
+                Token::Identifier("i".into()),
+                Token::Eof,
+            ]
+        );
+
+        let err = Lexer::new("1.5ei").tokenize().unwrap_err();
+        assert_eq!(err.len(), 1);
+        match &err[0] {
+            LexerError::InvalidNumberFormat { text, .. } => assert_eq!(text, "1.5e"),
+            _ => panic!("Expected InvalidNumberFormat"),
+        }
+    }
+
     #[test]
     fn test_unexpected_character() {
         let mut lexer = Lexer::new("@");
@@ -139,10 +263,507 @@ mod lexer_tests {
             filter_tokens(tokens),
             vec![
                 Token::Newline,
-                Token::Number(42.0),
-                Token::Number(3.0),
+                Token::Integer(42),
+                Token::Integer(3),
                 Token::Eof,
             ]
         );
     }
+
+    #[test]
+    fn test_logical_keywords() {
+        let mut lexer = Lexer::new("true and false or truth");
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(
+            filter_tokens(tokens),
+            vec![
+                Token::Boolean(true),
+                Token::And,
+                Token::Boolean(false),
+                Token::Or,
+                Token::Identifier("truth".into()),
+                Token::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_membership_keywords() {
+        let mut lexer = Lexer::new("x in xs not in inside");
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(
+            filter_tokens(tokens),
+            vec![
+                Token::Identifier("x".into()),
+                Token::In,
+                Token::Identifier("xs".into()),
+                Token::Not,
+                Token::In,
+                Token::Identifier("inside".into()),
+                Token::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_number_position() {
+        let mut lexer = Lexer::new("x = 12.3.4 + 1");
+        let err = lexer.tokenize().unwrap_err();
+        assert_eq!(err.len(), 1);
+        match &err[0] {
+            LexerError::InvalidNumberFormat {
+                text,
+                line,
+                col,
+                pos,
+                len,
+            } => {
+                assert_eq!(text, "12.3.4");
+                assert_eq!((*line, *col), (1, 5));
+                assert_eq!(*pos, 4);
+                assert_eq!(*len, 6);
+            }
+            _ => panic!("Expected InvalidNumberFormat"),
+        }
+    }
+
+    #[test]
+    fn test_range_operator() {
+        let mut lexer = Lexer::new("1..3 1.5..x");
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(
+            filter_tokens(tokens),
+            vec![
+                Token::Integer(1),
+                Token::DotDot,
+                Token::Integer(3),
+                Token::Number(1.5),
+                Token::DotDot,
+                Token::Identifier("x".into()),
+                Token::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strings() {
+        let mut lexer = Lexer::new("\"desc\" \"\"");
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(
+            filter_tokens(tokens),
+            vec![
+                Token::String("desc".into()),
+                Token::String("".into()),
+                Token::Eof,
+            ]
+        );
+
+        let err = Lexer::new("x = \"abc").tokenize().unwrap_err();
+        assert!(matches!(err[0], LexerError::UnterminatedString(1, 5)));
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let mut lexer = Lexer::new(r#""a\nb" "\t" "say \"hi\"" "back\\slash""#);
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(
+            filter_tokens(tokens),
+            vec![
+                Token::String("a\nb".into()),
+                Token::String("\t".into()),
+                Token::String("say \"hi\"".into()),
+                Token::String("back\\slash".into()),
+                Token::Eof,
+            ]
+        );
+
+        let err = Lexer::new(r#""a\qb" 1"#).tokenize().unwrap_err();
+        assert_eq!(err.len(), 1);
+        assert!(matches!(err[0], LexerError::InvalidEscape('q', 1, 3)));
+
+        let err = Lexer::new(r#""abc\""#).tokenize().unwrap_err();
+        assert!(matches!(err[0], LexerError::UnterminatedString(1, 1)));
+    }
+
+    #[test]
+    fn test_question_equal() {
+        let mut lexer = Lexer::new("x ?= 5 ? :");
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(
+            filter_tokens(tokens),
+            vec![
+                Token::Identifier("x".into()),
+                Token::QuestionEqual,
+                Token::Integer(5),
+                Token::Question,
+                Token::Colon,
+                Token::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_arrow() {
+        let mut lexer = Lexer::new("x -> x-1");
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(
+            filter_tokens(tokens),
+            vec![
+                Token::Identifier("x".into()),
+                Token::Arrow,
+                Token::Identifier("x".into()),
+                Token::Minus,
+                Token::Integer(1),
+                Token::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mistyped_comparison_operators() {
+        let errors = Lexer::new("x =< 5").tokenize().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Unknown operator '=<' at line 1, column 3 (did you mean '<='?)"
+        );
+        assert_eq!(errors[0].span_len(), 2);
+
+        let errors = Lexer::new("y => 2").tokenize().unwrap_err();
+        assert!(matches!(
+            errors[0],
+            LexerError::MistypedOperator {
+                expected: ">=",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_mistyped_operator_recovers_as_intended_token() {
+        let (tokens, errors) = Lexer::new("x =< 5").tokenize_recovering();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            filter_tokens(tokens),
+            vec![
+                Token::Identifier("x".into()),
+                Token::LessEqual,
+                Token::Integer(5),
+                Token::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        let mut lexer = Lexer::new("x += 1 -= 2 *= 3 /= 4 + -5");
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(
+            filter_tokens(tokens),
+            vec![
+                Token::Identifier("x".into()),
+                Token::PlusEqual,
+                Token::Integer(1),
+                Token::MinusEqual,
+                Token::Integer(2),
+                Token::StarEqual,
+                Token::Integer(3),
+                Token::SlashEqual,
+                Token::Integer(4),
+                Token::Plus,
+                Token::Minus,
+                Token::Integer(5),
+                Token::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_thousands_separator() {
+        let mut lexer = Lexer::new("1,234.56 + 12,345,678").with_thousands_separator(',');
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(
+            filter_tokens(tokens),
+            vec![
+                Token::Number(1234.56),
+                Token::Plus,
+                Token::Integer(12345678),
+                Token::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_thousands_separator_inside_brackets() {
+        let mut lexer = Lexer::new("f(1,234) [5,678]").with_thousands_separator(',');
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(
+            filter_tokens(tokens),
+            vec![
+                Token::Identifier("f".into()),
+                Token::LParen,
+                Token::Integer(1),
+                Token::Comma,
+                Token::Integer(234),
+                Token::RParen,
+                Token::LBracket,
+                Token::Integer(5),
+                Token::Comma,
+                Token::Integer(678),
+                Token::RBracket,
+                Token::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_thousands_separator_needs_full_groups() {
+        // Anything that is not a group of three digits keeps the comma
+        for source in ["1,23", "1,2345", "1234,567", "1.5,000", "1,"] {
+            let mut lexer = Lexer::new(source).with_thousands_separator(',');
+            let tokens = filter_tokens(lexer.tokenize().unwrap());
+            assert!(tokens.contains(&Token::Comma), "{}", source);
+        }
+
+        // Off by default
+        let tokens = filter_tokens(Lexer::new("1,234").tokenize().unwrap());
+        assert_eq!(
+            tokens,
+            vec![Token::Integer(1), Token::Comma, Token::Integer(234), Token::Eof]
+        );
+    }
+
+    #[test]
+    fn test_token_spans() {
+        let tokens = Lexer::new("foo_bar <= 1_000.5 \"a\\n\" ..").tokenize().unwrap();
+        let spans: Vec<(Token, usize, usize)> = tokens
+            .into_iter()
+            .filter(|t| t.value != Token::Whitespace)
+            .map(|t| (t.value, t.span.pos, t.span.len))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                (Token::Identifier("foo_bar".into()), 0, 7),
+                (Token::LessEqual, 8, 2),
+                (Token::Number(1000.5), 11, 7),
+                (Token::String("a\n".into()), 19, 5),
+                (Token::DotDot, 25, 2),
+                (Token::Eof, 27, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_token_span_on_later_line() {
+        let tokens = Lexer::new("1\n  if").tokenize().unwrap();
+        let keyword = tokens.iter().find(|t| t.value == Token::If).unwrap();
+        assert_eq!((keyword.span.line, keyword.span.col), (2, 3));
+        assert_eq!(keyword.span.len, 2);
+    }
+
+    #[test]
+    fn test_logical_symbols() {
+        let mut lexer = Lexer::new("a && b || c");
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(
+            filter_tokens(tokens),
+            vec![
+                Token::Identifier("a".into()),
+                Token::AmpAmp,
+                Token::Identifier("b".into()),
+                Token::PipePipe,
+                Token::Identifier("c".into()),
+                Token::Eof,
+            ]
+        );
+
+        let errors = Lexer::new("a & b | c").tokenize().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0], LexerError::UnexpectedCharacter('&', 1, 3)));
+
+        let mut lexer = Lexer::new("|x|");
+        assert_eq!(
+            filter_tokens(lexer.tokenize().unwrap()),
+            vec![
+                Token::Pipe,
+                Token::Identifier("x".into()),
+                Token::Pipe,
+                Token::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_raw_identifiers() {
+        let mut lexer = Lexer::new("`and` = `my var` + if_");
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(tokens[0].span.len, 5);
+        assert_eq!(
+            filter_tokens(tokens),
+            vec![
+                Token::Identifier("and".into()),
+                Token::Equal,
+                Token::Identifier("my var".into()),
+                Token::Plus,
+                Token::Identifier("if_".into()),
+                Token::Eof,
+            ]
+        );
+
+        let errors = Lexer::new("x = `abc\n1").tokenize().unwrap_err();
+        assert!(matches!(errors[0], LexerError::UnterminatedIdentifier(1, 5)));
+        let errors = Lexer::new("``").tokenize().unwrap_err();
+        assert!(matches!(errors[0], LexerError::EmptyIdentifier(1, 1)));
+    }
+
+    #[test]
+    fn test_span_columns_after_newlines() {
+        let source = "a = 1\r\n/* two\nlines */ s = \"x\ny\"\n\tfoo + 42";
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let span = |token: Token| {
+            let spanned = tokens.iter().find(|t| t.value == token).unwrap();
+            (spanned.span.line, spanned.span.col, spanned.span.len)
+        };
+
+        assert_eq!(span(Token::Identifier("a".into())), (1, 1, 1));
+        // The comment and the string both span a newline
+        assert_eq!(span(Token::Identifier("s".into())), (3, 10, 1));
+        assert_eq!(span(Token::String("x\ny".into())), (3, 14, 5));
+        assert_eq!(span(Token::Identifier("foo".into())), (5, 2, 3));
+        assert_eq!(span(Token::Plus), (5, 6, 1));
+        assert_eq!(span(Token::Integer(42)), (5, 8, 2));
+    }
+
+    #[test]
+    fn test_errors_for_several_malformed_numbers() {
+        let (tokens, errors) = Lexer::new("a = 1.2.3+4\nb = 1e5e3 * c").tokenize_recovering();
+
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(matches!(
+            &errors[0],
+            LexerError::InvalidNumberFormat { text, line: 1, col: 5, .. } if text == "1.2.3"
+        ));
+        assert!(matches!(
+            &errors[1],
+            LexerError::InvalidNumberFormat { text, line: 2, col: 5, .. } if text == "1e5e3"
+        ));
+
+        // Nothing of the bad numbers is left over, and later tokens keep their columns
+        assert_eq!(
+            positioned_tokens(tokens),
+            vec![
+                (Token::Identifier("a".into()), 1, 1),
+                (Token::Equal, 1, 3),
+                (Token::Plus, 1, 10),
+                (Token::Integer(4), 1, 11),
+                (Token::Newline, 1, 12),
+                (Token::Identifier("b".into()), 2, 1),
+                (Token::Equal, 2, 3),
+                (Token::Star, 2, 11),
+                (Token::Identifier("c".into()), 2, 13),
+                (Token::Eof, 2, 14),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_positions_of_numbers_and_operators() {
+        let tokens = Lexer::new("x = 3.5 * (y + 12)\n  z <= 0x1F").tokenize().unwrap();
+        assert_eq!(
+            positioned_tokens(tokens),
+            vec![
+                (Token::Identifier("x".into()), 1, 1),
+                (Token::Equal, 1, 3),
+                (Token::Number(3.5), 1, 5),
+                (Token::Star, 1, 9),
+                (Token::LParen, 1, 11),
+                (Token::Identifier("y".into()), 1, 12),
+                (Token::Plus, 1, 14),
+                (Token::Integer(12), 1, 16),
+                (Token::RParen, 1, 18),
+                (Token::Newline, 1, 19),
+                (Token::Identifier("z".into()), 2, 3),
+                (Token::LessEqual, 2, 5),
+                (Token::Integer(31), 2, 8),
+                (Token::Eof, 2, 12),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_positions_of_multi_character_operators() {
+        let tokens = Lexer::new("a += 1\nb != c && d >= -2..5").tokenize().unwrap();
+        assert_eq!(
+            positioned_tokens(tokens),
+            vec![
+                (Token::Identifier("a".into()), 1, 1),
+                (Token::PlusEqual, 1, 3),
+                (Token::Integer(1), 1, 6),
+                (Token::Newline, 1, 7),
+                (Token::Identifier("b".into()), 2, 1),
+                (Token::ExclamationEqual, 2, 3),
+                (Token::Identifier("c".into()), 2, 6),
+                (Token::AmpAmp, 2, 8),
+                (Token::Identifier("d".into()), 2, 11),
+                (Token::GreaterEqual, 2, 13),
+                (Token::Minus, 2, 16),
+                (Token::Integer(2), 2, 17),
+                (Token::DotDot, 2, 18),
+                (Token::Integer(5), 2, 20),
+                (Token::Eof, 2, 21),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_positions_after_comments_and_strings() {
+        let source = "// note\n\"a\\nb\" + 1 /* c */ - 2.5e1\n\tf(x)";
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        assert_eq!(
+            positioned_tokens(tokens),
+            vec![
+                (Token::Newline, 1, 8),
+                (Token::String("a\nb".into()), 2, 1),
+                (Token::Plus, 2, 8),
+                (Token::Integer(1), 2, 10),
+                (Token::Minus, 2, 20),
+                (Token::Number(25.0), 2, 22),
+                (Token::Newline, 2, 27),
+                (Token::Identifier("f".into()), 3, 2),
+                (Token::LParen, 3, 3),
+                (Token::Identifier("x".into()), 3, 4),
+                (Token::RParen, 3, 5),
+                (Token::Eof, 3, 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_double_slash_modes() {
+        let source = "7 // 2 # half\n/* c */ 1";
+
+        let tokens = Lexer::new(source)
+            .with_double_slash(DoubleSlashMode::FloorDiv)
+            .tokenize()
+            .unwrap();
+        assert_eq!(tokens[2].span.len, 2);
+        assert_eq!(
+            filter_tokens(tokens),
+            vec![
+                Token::Integer(7),
+                Token::SlashSlash,
+                Token::Integer(2),
+                Token::Newline,
+                Token::Integer(1),
+                Token::Eof,
+            ]
+        );
+
+        // By default `//` starts a comment, and `#` is not a comment
+        let tokens = Lexer::new("7 // 2").tokenize().unwrap();
+        assert_eq!(filter_tokens(tokens), vec![Token::Integer(7), Token::Eof]);
+        let errors = Lexer::new("# note").tokenize().unwrap_err();
+        assert!(matches!(errors[0], LexerError::UnexpectedCharacter('#', 1, 1)));
+    }
 }
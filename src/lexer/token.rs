@@ -5,6 +5,8 @@ pub struct Span {
     pub line: usize,
     pub col: usize,
     pub pos: usize,
+    // Number of characters the token covers in the source
+    pub len: usize,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -16,8 +18,13 @@ pub struct Spanned<T> {
 pub type SpannedToken = Spanned<Token>;
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Token {
     Number(f64),
+    // A literal without a decimal point or exponent
+    Integer(i64),
+    String(String),
+    Boolean(bool),
     Identifier(String),
     Plus,
     Minus,
@@ -32,7 +39,17 @@ pub enum Token {
     LBracket,
     RBracket,
     Comma,
+    DotDot,
+    Arrow,
+    Question,
+    QuestionEqual,
+    Colon,
     Equal,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    SlashSlash,
     EqualEqual,
     Less,
     Greater,
@@ -41,6 +58,17 @@ pub enum Token {
     Exclamation,
     ExclamationEqual,
     Semicolon,
+    In,
+    Not,
+    If,
+    Then,
+    Else,
+    And,
+    Or,
+    AmpAmp,
+    PipePipe,
+    Pipe,
+    Const,
     Newline,
     Whitespace,
     Eof,
@@ -49,7 +77,9 @@ pub enum Token {
 impl Token {
     pub fn description(&self) -> String {
         match self {
-            Token::Number(_) => "number".to_string(),
+            Token::Number(_) | Token::Integer(_) => "number".to_string(),
+            Token::String(_) => "string".to_string(),
+            Token::Boolean(_) => "boolean".to_string(),
             Token::Identifier(_) => "identifier".to_string(),
             Token::Eof => "end of input".to_string(),
             Token::Newline => "newline".to_string(),
@@ -58,10 +88,72 @@ impl Token {
         }
     }
 
-    pub fn span(self, line: usize, col: usize, pos: usize) -> SpannedToken {
+    // Variant name, for consumers such as syntax highlighters
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Token::Number(_) => "Number",
+            Token::Integer(_) => "Integer",
+            Token::String(_) => "String",
+            Token::Boolean(_) => "Boolean",
+            Token::Identifier(_) => "Identifier",
+            Token::Plus => "Plus",
+            Token::Minus => "Minus",
+            Token::Star => "Star",
+            Token::Slash => "Slash",
+            Token::Percent => "Percent",
+            Token::Caret => "Caret",
+            Token::LParen => "LParen",
+            Token::RParen => "RParen",
+            Token::LBrace => "LBrace",
+            Token::RBrace => "RBrace",
+            Token::LBracket => "LBracket",
+            Token::RBracket => "RBracket",
+            Token::Comma => "Comma",
+            Token::DotDot => "DotDot",
+            Token::Arrow => "Arrow",
+            Token::Question => "Question",
+            Token::QuestionEqual => "QuestionEqual",
+            Token::Colon => "Colon",
+            Token::Equal => "Equal",
+            Token::PlusEqual => "PlusEqual",
+            Token::MinusEqual => "MinusEqual",
+            Token::StarEqual => "StarEqual",
+            Token::SlashEqual => "SlashEqual",
+            Token::SlashSlash => "SlashSlash",
+            Token::EqualEqual => "EqualEqual",
+            Token::Less => "Less",
+            Token::Greater => "Greater",
+            Token::LessEqual => "LessEqual",
+            Token::GreaterEqual => "GreaterEqual",
+            Token::Exclamation => "Exclamation",
+            Token::ExclamationEqual => "ExclamationEqual",
+            Token::Semicolon => "Semicolon",
+            Token::In => "In",
+            Token::Not => "Not",
+            Token::If => "If",
+            Token::Then => "Then",
+            Token::Else => "Else",
+            Token::And => "And",
+            Token::Or => "Or",
+            Token::AmpAmp => "AmpAmp",
+            Token::PipePipe => "PipePipe",
+            Token::Pipe => "Pipe",
+            Token::Const => "Const",
+            Token::Newline => "Newline",
+            Token::Whitespace => "Whitespace",
+            Token::Eof => "Eof",
+        }
+    }
+
+    pub fn span(self, line: usize, col: usize, pos: usize, len: usize) -> SpannedToken {
         Spanned {
             value: self,
-            span: Span { line, col, pos },
+            span: Span {
+                line,
+                col,
+                pos,
+                len,
+            },
         }
     }
 }
@@ -70,6 +162,9 @@ impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Token::Number(n) => write!(f, "{}", n),
+            Token::Integer(n) => write!(f, "{}", n),
+            Token::String(s) => write!(f, "{:?}", s),
+            Token::Boolean(b) => write!(f, "{}", b),
             Token::Identifier(s) => write!(f, "{}", s),
             Token::Plus => write!(f, "+"),
             Token::Minus => write!(f, "-"),
@@ -84,7 +179,17 @@ impl fmt::Display for Token {
             Token::LBracket => write!(f, "["),
             Token::RBracket => write!(f, "]"),
             Token::Comma => write!(f, ","),
+            Token::DotDot => write!(f, ".."),
+            Token::Arrow => write!(f, "->"),
+            Token::Question => write!(f, "?"),
+            Token::QuestionEqual => write!(f, "?="),
+            Token::Colon => write!(f, ":"),
             Token::Equal => write!(f, "="),
+            Token::PlusEqual => write!(f, "+="),
+            Token::MinusEqual => write!(f, "-="),
+            Token::StarEqual => write!(f, "*="),
+            Token::SlashEqual => write!(f, "/="),
+            Token::SlashSlash => write!(f, "//"),
             Token::EqualEqual => write!(f, "=="),
             Token::Less => write!(f, "<"),
             Token::Greater => write!(f, ">"),
@@ -93,6 +198,17 @@ impl fmt::Display for Token {
             Token::Exclamation => write!(f, "!"),
             Token::ExclamationEqual => write!(f, "!="),
             Token::Semicolon => write!(f, ";"),
+            Token::In => write!(f, "in"),
+            Token::Not => write!(f, "not"),
+            Token::If => write!(f, "if"),
+            Token::Then => write!(f, "then"),
+            Token::Else => write!(f, "else"),
+            Token::And => write!(f, "and"),
+            Token::Or => write!(f, "or"),
+            Token::AmpAmp => write!(f, "&&"),
+            Token::PipePipe => write!(f, "||"),
+            Token::Pipe => write!(f, "|"),
+            Token::Const => write!(f, "const"),
             Token::Newline => write!(f, "\\n"),
             Token::Whitespace => write!(f, " "),
             Token::Eof => write!(f, "end of file"),
@@ -4,12 +4,24 @@ use crate::lexer::{
     token::{SpannedToken, Token},
 };
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DoubleSlashMode {
+    #[default]
+    Comment,
+    // `//` is floor division, and `#` starts a line comment instead
+    FloorDiv,
+}
+
 pub struct Lexer<'a> {
     chars: std::str::Chars<'a>,
     current_char: Option<char>,
     pos: usize,
     line: usize,
     column: usize,
+    thousands_separator: Option<char>,
+    // Open brackets of any kind; separators are never read inside them
+    depth: usize,
+    double_slash: DoubleSlashMode,
 }
 
 impl<'a> Lexer<'a> {
@@ -21,19 +33,33 @@ impl<'a> Lexer<'a> {
             pos: 0,
             line: 1,
             column: 1,
+            thousands_separator: None,
+            depth: 0,
+            double_slash: DoubleSlashMode::default(),
         };
         lexer.current_char = lexer.chars.next();
         lexer
     }
 
+    // Opt-in: `1,234.5` lexes as one number when the separator is `,`
+    pub fn with_thousands_separator(mut self, separator: char) -> Self {
+        self.thousands_separator = Some(separator);
+        self
+    }
+
+    // Opt-in: `7 // 2` is floor division, for people used to Python
+    pub fn with_double_slash(mut self, mode: DoubleSlashMode) -> Self {
+        self.double_slash = mode;
+        self
+    }
+
     pub fn advance(&mut self) {
         log::debug!("advance() called at line {}, column {}", self.line, self.column);
-        if let Some(c) = self.current_char {
-            if c == '\n' {
-                log::debug!("advance() detected newline character");
-                self.line += 1;
-                self.column = 0;
-            }
+        if let Some('\n') = self.current_char {
+            log::debug!("advance() detected newline character");
+            self.line += 1;
+            // Incremented below, so the character after the newline is at column 1
+            self.column = 0;
         }
 
         self.current_char = self.chars.next();
@@ -45,6 +71,13 @@ impl<'a> Lexer<'a> {
         self.chars.clone().next()
     }
 
+    // The current separator is followed by exactly three digits
+    fn is_thousands_group(&self) -> bool {
+        let mut rest = self.chars.clone();
+        let group_is_digits = (0..3).all(|_| rest.next().is_some_and(|c| c.is_ascii_digit()));
+        group_is_digits && !rest.next().is_some_and(|c| c.is_ascii_digit())
+    }
+
     fn whitespace(&mut self, tokens: &mut Vec<SpannedToken>) {
         log::debug!("whitespace() called at line {}, column {}", self.line, self.column);
 
@@ -65,6 +98,16 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    // Skips to the end of the line, leaving the newline itself
+    fn line_comment(&mut self) {
+        while let Some(c) = self.current_char {
+            if c == '\n' {
+                break;
+            }
+            self.advance();
+        }
+    }
+
     fn identifier(&mut self) -> Token {
         log::debug!("identifier() called at line {}, column {}", self.line, self.column);
         let mut id_str = String::new();
@@ -79,46 +122,129 @@ impl<'a> Lexer<'a> {
             }
         }
 
-        Token::Identifier(id_str)
+        match id_str.as_str() {
+            "in" => Token::In,
+            "not" => Token::Not,
+            "if" => Token::If,
+            "then" => Token::Then,
+            "else" => Token::Else,
+            "and" => Token::And,
+            "or" => Token::Or,
+            "true" => Token::Boolean(true),
+            "false" => Token::Boolean(false),
+            "const" => Token::Const,
+            _ => Token::Identifier(id_str),
+        }
+    }
+
+    // `` `and` `` or `` `my var` ``: any name, even a keyword, as an identifier
+    fn raw_identifier(&mut self) -> Result<Token, LexerError> {
+        log::debug!("raw_identifier() called at line {}, column {}", self.line, self.column);
+        let start_line = self.line;
+        let start_col = self.column;
+
+        self.advance();
+        let mut name = String::new();
+
+        while let Some(c) = self.current_char {
+            match c {
+                '`' => {
+                    self.advance();
+                    if name.is_empty() {
+                        return Err(LexerError::EmptyIdentifier(start_line, start_col));
+                    }
+                    return Ok(Token::Identifier(name));
+                }
+                // The newline is left for the next statement
+                '\n' => break,
+                _ => {
+                    name.push(c);
+                    self.advance();
+                }
+            }
+        }
+
+        log::warn!("raw_identifier() reached end of line before closing backtick");
+        Err(LexerError::UnterminatedIdentifier(start_line, start_col))
     }
 
     fn number(&mut self) -> Result<Token, LexerError> {
         log::debug!("number() called at line {}, column {}", self.line, self.column);
         let start_line = self.line;
         let start_col = self.column;
+        let start_pos = self.pos;
+
+        if self.current_char == Some('0') {
+            let radix = match self.peek() {
+                Some('x' | 'X') => Some(16),
+                Some('b' | 'B') => Some(2),
+                Some('o' | 'O') => Some(8),
+                _ => None,
+            };
+            if let Some(radix) = radix {
+                return self.radix_number(radix, start_line, start_col, start_pos);
+            }
+        }
 
         let mut num_str = String::new();
         let mut has_dot = false;
         let mut has_exponent = false;
+        let mut misplaced_separator = false;
+        // Digits since the start or the last thousands separator
+        let mut group_len = 0;
+        let mut grouped = false;
 
         while let Some(c) = self.current_char {
             match c {
                 '0'..='9' => {
                     log::debug!("number() adding digit to number: {}", c);
                     num_str.push(c);
+                    group_len += 1;
                     self.advance();
                 }
 
+                // Only in the integer part, outside brackets, between well-formed groups
+                c if Some(c) == self.thousands_separator
+                    && self.depth == 0
+                    && !has_dot
+                    && !has_exponent
+                    && (if grouped { group_len == 3 } else { (1..=3).contains(&group_len) })
+                    && self.is_thousands_group() =>
+                {
+                    log::debug!("number() skipping thousands separator");
+                    grouped = true;
+                    group_len = 0;
+                    self.advance();
+                }
+
+                // Digit separators are only allowed between two digits
+                '_' => {
+                    let between_digits = num_str.ends_with(|p: char| p.is_ascii_digit())
+                        && self.peek().is_some_and(|n| n.is_ascii_digit());
+                    if !between_digits {
+                        log::warn!("number() detected misplaced digit separator");
+                        misplaced_separator = true;
+                    }
+                    num_str.push(c);
+                    self.advance();
+                }
+
+                '.' if self.peek() == Some('.') => break,
+
                 '.' => {
                     if has_dot || has_exponent {
                         log::warn!("number() detected invalid number format with multiple dots or dot after exponent");
                         num_str.push(c);
                         self.advance();
+                        self.rest_of_number(&mut num_str);
 
-                        while let Some(nc) = self.current_char {
-                            match nc {
-                                '0'..='9' | '.' | 'e' | 'E' | '+' | '-' => {
-                                    num_str.push(nc);
-                                    self.advance();
-                                }
-                                _ => break,
-                            }
-                        }
-
-
-                        return Err(LexerError::InvalidNumberFormat(
-                            num_str, start_line, start_col,
-                        ));
+                        return Err(LexerError::InvalidNumberFormat {
+                            len: num_str.chars().count(),
+                            text: num_str,
+                            line: start_line,
+                            col: start_col,
+                            pos: start_pos,
+                        });
                     }
 
                     log::debug!("number() adding decimal point to number");
@@ -132,10 +258,15 @@ impl<'a> Lexer<'a> {
                     if has_exponent {
                         num_str.push(c);
                         self.advance();
+                        self.rest_of_number(&mut num_str);
                         log::warn!("number() detected invalid number format with multiple exponents");
-                        return Err(LexerError::InvalidNumberFormat(
-                            num_str, start_line, start_col,
-                        ));
+                        return Err(LexerError::InvalidNumberFormat {
+                            len: num_str.chars().count(),
+                            text: num_str,
+                            line: start_line,
+                            col: start_col,
+                            pos: start_pos,
+                        });
                     }
 
 
@@ -155,19 +286,162 @@ impl<'a> Lexer<'a> {
             }
         }
 
-        num_str
+        if misplaced_separator {
+            return Err(LexerError::InvalidNumberFormat {
+                len: num_str.chars().count(),
+                text: num_str,
+                line: start_line,
+                col: start_col,
+                pos: start_pos,
+            });
+        }
+
+        let digits = num_str.replace('_', "");
+        // Too large for an integer, it is still a number
+        if !has_dot
+            && !has_exponent
+            && let Ok(n) = digits.parse::<i64>()
+        {
+            return Ok(Token::Integer(n));
+        }
+        digits
             .parse::<f64>()
             .map(Token::Number)
-            .map_err(|_| LexerError::InvalidNumberFormat(num_str, start_line, start_col))
+            .map_err(|_| LexerError::InvalidNumberFormat {
+                len: num_str.chars().count(),
+                text: num_str,
+                line: start_line,
+                col: start_col,
+                pos: start_pos,
+            })
+    }
+
+    // Consumes what is left of a malformed number, so none of it is read as a new
+    // token. A sign only belongs to the number right after an exponent: in
+    // `1.2.3+4`, the `+ 4` is left alone.
+    fn rest_of_number(&mut self, num_str: &mut String) {
+        while let Some(c) = self.current_char {
+            let is_exponent_sign = matches!(c, '+' | '-') && num_str.ends_with(['e', 'E']);
+            if !(c.is_ascii_digit() || matches!(c, '.' | '_' | 'e' | 'E') || is_exponent_sign) {
+                break;
+            }
+            if c == '.' && self.peek() == Some('.') {
+                break;
+            }
+            num_str.push(c);
+            self.advance();
+        }
+    }
+
+    // `0x1F`, `0b1010` and `0o17` integer literals
+    fn radix_number(
+        &mut self,
+        radix: u32,
+        start_line: usize,
+        start_col: usize,
+        start_pos: usize,
+    ) -> Result<Token, LexerError> {
+        log::debug!("radix_number() called with radix {}", radix);
+        let mut num_str = String::new();
+
+        // The `0` and the radix marker
+        for _ in 0..2 {
+            if let Some(c) = self.current_char {
+                num_str.push(c);
+            }
+            self.advance();
+        }
+
+        let mut digits = String::new();
+        while let Some(c) = self.current_char {
+            if !c.is_ascii_alphanumeric() {
+                break;
+            }
+            digits.push(c);
+            num_str.push(c);
+            self.advance();
+        }
+
+        u64::from_str_radix(&digits, radix)
+            .map(|n| i64::try_from(n).map_or(Token::Number(n as f64), Token::Integer))
+            .map_err(|_| {
+                log::warn!("radix_number() detected invalid digits '{}'", digits);
+                LexerError::InvalidNumberFormat {
+                    len: num_str.chars().count(),
+                    text: num_str,
+                    line: start_line,
+                    col: start_col,
+                    pos: start_pos,
+                }
+            })
+    }
+
+    fn string(&mut self) -> Result<Token, LexerError> {
+        log::debug!("string() called at line {}, column {}", self.line, self.column);
+        let start_line = self.line;
+        let start_col = self.column;
+
+        self.advance();
+        let mut value = String::new();
+        let mut invalid_escape = None;
+
+        while let Some(c) = self.current_char {
+            match c {
+                '"' => {
+                    self.advance();
+                    return match invalid_escape {
+                        Some(err) => Err(err),
+                        None => Ok(Token::String(value)),
+                    };
+                }
+
+                '\\' => {
+                    let (line, col) = (self.line, self.column);
+                    self.advance();
+                    match self.current_char {
+                        Some('n') => value.push('\n'),
+                        Some('t') => value.push('\t'),
+                        Some('"') => value.push('"'),
+                        Some('\\') => value.push('\\'),
+                        Some(other) => {
+                            log::warn!("string() found invalid escape sequence '\\{}'", other);
+                            invalid_escape
+                                .get_or_insert(LexerError::InvalidEscape(other, line, col));
+                        }
+                        None => break,
+                    }
+                    self.advance();
+                }
+
+                _ => {
+                    value.push(c);
+                    self.advance();
+                }
+            }
+        }
+
+        log::warn!("string() reached end of input before closing quote");
+        Err(LexerError::UnterminatedString(start_line, start_col))
     }
 
     fn push_token(&mut self, tokens: &mut Vec<SpannedToken>, token: Token) {
-        let spanned = token.span(self.line, self.column, self.pos);
+        let spanned = token.span(self.line, self.column, self.pos, 1);
         tokens.push(spanned);
         self.advance();
     }
 
     pub fn tokenize(&mut self) -> Result<Vec<SpannedToken>, Vec<LexerError>> {
+        let (tokens, errors) = self.tokenize_recovering();
+
+        if !errors.is_empty() {
+            Err(errors)
+        } else {
+            Ok(tokens)
+        }
+    }
+
+    // Skips over invalid input instead of stopping, so the tokens can still be parsed
+    pub fn tokenize_recovering(&mut self) -> (Vec<SpannedToken>, Vec<LexerError>) {
         log::debug!("tokenize() called");
         let mut tokens = Vec::new();
         let mut errors = Vec::new();
@@ -178,9 +452,33 @@ impl<'a> Lexer<'a> {
             let start_pos = self.pos;
 
             match c {
+                '.' if self.peek() == Some('.') => {
+                    self.advance();
+                    tokens.push(Token::DotDot.span(start_line, start_col, start_pos, 2));
+                    self.advance();
+                }
                 '0'..='9' | '.' => match self.number() {
                     Ok(token) => {
-                        tokens.push(token.span(start_line, start_col, start_pos));
+                        let len = self.pos - start_pos;
+                        tokens.push(token.span(start_line, start_col, start_pos, len));
+                    }
+                    Err(err) => {
+                        errors.push(err);
+                    }
+                },
+                '"' => match self.string() {
+                    Ok(token) => {
+                        let len = self.pos - start_pos;
+                        tokens.push(token.span(start_line, start_col, start_pos, len));
+                    }
+                    Err(err) => {
+                        errors.push(err);
+                    }
+                },
+                '`' => match self.raw_identifier() {
+                    Ok(token) => {
+                        let len = self.pos - start_pos;
+                        tokens.push(token.span(start_line, start_col, start_pos, len));
                     }
                     Err(err) => {
                         errors.push(err);
@@ -188,21 +486,49 @@ impl<'a> Lexer<'a> {
                 },
                 'a'..='z' | 'A'..='Z' | '_' => {
                     let token = self.identifier();
-                    tokens.push(token.span(start_line, start_col, start_pos));
+                    let len = self.pos - start_pos;
+                    tokens.push(token.span(start_line, start_col, start_pos, len));
+                }
+                '+' => {
+                    if self.peek() == Some('=') {
+                        self.advance();
+                        tokens.push(Token::PlusEqual.span(start_line, start_col, start_pos, 2));
+                        self.advance();
+                    } else {
+                        self.push_token(&mut tokens, Token::Plus);
+                    }
+                }
+                '-' => {
+                    if self.peek() == Some('>') {
+                        self.advance();
+                        tokens.push(Token::Arrow.span(start_line, start_col, start_pos, 2));
+                        self.advance();
+                    } else if self.peek() == Some('=') {
+                        self.advance();
+                        tokens.push(Token::MinusEqual.span(start_line, start_col, start_pos, 2));
+                        self.advance();
+                    } else {
+                        self.push_token(&mut tokens, Token::Minus);
+                    }
+                }
+                '*' => {
+                    if self.peek() == Some('=') {
+                        self.advance();
+                        tokens.push(Token::StarEqual.span(start_line, start_col, start_pos, 2));
+                        self.advance();
+                    } else {
+                        self.push_token(&mut tokens, Token::Star);
+                    }
                 }
-                '+' => self.push_token(&mut tokens, Token::Plus),
-                '-' => self.push_token(&mut tokens, Token::Minus),
-                '*' => self.push_token(&mut tokens, Token::Star),
                 '/' => {
-                    if self.peek() == Some('/') {
+                    if self.peek() == Some('/') && self.double_slash == DoubleSlashMode::FloorDiv {
                         self.advance();
+                        tokens.push(Token::SlashSlash.span(start_line, start_col, start_pos, 2));
                         self.advance();
-                        while let Some(c) = self.current_char {
-                            if c == '\n' {
-                                break;
-                            }
-                            self.advance();
-                        }
+                    } else if self.peek() == Some('/') {
+                        self.advance();
+                        self.advance();
+                        self.line_comment();
                     } else if self.peek() == Some('*') {
                         self.advance();
                         self.advance();
@@ -214,23 +540,51 @@ impl<'a> Lexer<'a> {
                             }
                             self.advance();
                         }
+                    } else if self.peek() == Some('=') {
+                        self.advance();
+                        tokens.push(Token::SlashEqual.span(start_line, start_col, start_pos, 2));
+                        self.advance();
                     } else {
                         self.push_token(&mut tokens, Token::Slash);
                     }
                 }
+                '#' if self.double_slash == DoubleSlashMode::FloorDiv => self.line_comment(),
                 '%' => self.push_token(&mut tokens, Token::Percent),
                 '^' => self.push_token(&mut tokens, Token::Caret),
-                '(' => self.push_token(&mut tokens, Token::LParen),
-                ')' => self.push_token(&mut tokens, Token::RParen),
-                '[' => self.push_token(&mut tokens, Token::LBracket),
-                ']' => self.push_token(&mut tokens, Token::RBracket),
-                '{' => self.push_token(&mut tokens, Token::LBrace),
-                '}' => self.push_token(&mut tokens, Token::RBrace),
+                '(' | '[' | '{' => {
+                    self.depth += 1;
+                    let token = match c {
+                        '(' => Token::LParen,
+                        '[' => Token::LBracket,
+                        _ => Token::LBrace,
+                    };
+                    self.push_token(&mut tokens, token);
+                }
+                ')' | ']' | '}' => {
+                    self.depth = self.depth.saturating_sub(1);
+                    let token = match c {
+                        ')' => Token::RParen,
+                        ']' => Token::RBracket,
+                        _ => Token::RBrace,
+                    };
+                    self.push_token(&mut tokens, token);
+                }
                 ',' => self.push_token(&mut tokens, Token::Comma),
+                '?' => {
+                    if self.peek() == Some('=') {
+                        self.advance();
+                        tokens.push(Token::QuestionEqual.span(start_line, start_col, start_pos, 2));
+                        self.advance();
+                    } else {
+                        self.push_token(&mut tokens, Token::Question);
+                    }
+                }
+                ':' => self.push_token(&mut tokens, Token::Colon),
                 '!' => {
                     if self.peek() == Some('=') {
                         self.advance();
-                        tokens.push(Token::ExclamationEqual.span(start_line, start_col, start_pos));
+                        let token = Token::ExclamationEqual;
+                        tokens.push(token.span(start_line, start_col, start_pos, 2));
                         self.advance();
                     } else {
                         self.push_token(&mut tokens, Token::Exclamation);
@@ -239,7 +593,24 @@ impl<'a> Lexer<'a> {
                 '=' => {
                     if self.peek() == Some('=') {
                         self.advance();
-                        tokens.push(Token::EqualEqual.span(start_line, start_col, start_pos));
+                        tokens.push(Token::EqualEqual.span(start_line, start_col, start_pos, 2));
+                        self.advance();
+                    } else if let Some(next @ ('<' | '>')) = self.peek() {
+                        // `=<` and `=>` are almost always a swapped `<=` / `>=`
+                        let (found, expected, token) = if next == '<' {
+                            ("=<", "<=", Token::LessEqual)
+                        } else {
+                            ("=>", ">=", Token::GreaterEqual)
+                        };
+                        log::warn!("tokenize() found mistyped operator '{}'", found);
+                        errors.push(LexerError::MistypedOperator {
+                            found,
+                            expected,
+                            line: start_line,
+                            col: start_col,
+                        });
+                        self.advance();
+                        tokens.push(token.span(start_line, start_col, start_pos, 2));
                         self.advance();
                     } else {
                         self.push_token(&mut tokens, Token::Equal);
@@ -248,7 +619,7 @@ impl<'a> Lexer<'a> {
                 '<' => {
                     if self.peek() == Some('=') {
                         self.advance();
-                        tokens.push(Token::LessEqual.span(start_line, start_col, start_pos));
+                        tokens.push(Token::LessEqual.span(start_line, start_col, start_pos, 2));
                         self.advance();
                     } else {
                         self.push_token(&mut tokens, Token::Less);
@@ -257,12 +628,20 @@ impl<'a> Lexer<'a> {
                 '>' => {
                     if self.peek() == Some('=') {
                         self.advance();
-                        tokens.push(Token::GreaterEqual.span(start_line, start_col, start_pos));
+                        tokens.push(Token::GreaterEqual.span(start_line, start_col, start_pos, 2));
                         self.advance();
                     } else {
                         self.push_token(&mut tokens, Token::Greater);
                     }
                 }
+                // Only the doubled form of `&` exists; a single `|` is an absolute value bar
+                '&' | '|' if self.peek() == Some(c) => {
+                    let token = if c == '&' { Token::AmpAmp } else { Token::PipePipe };
+                    self.advance();
+                    tokens.push(token.span(start_line, start_col, start_pos, 2));
+                    self.advance();
+                }
+                '|' => self.push_token(&mut tokens, Token::Pipe),
                 ';' => self.push_token(&mut tokens, Token::Semicolon),
                 c if c.is_whitespace() => self.whitespace(&mut tokens),
                 _ => {
@@ -272,12 +651,8 @@ impl<'a> Lexer<'a> {
             }
         }
 
-        tokens.push(Token::Eof.span(self.line, self.column, self.pos));
+        tokens.push(Token::Eof.span(self.line, self.column, self.pos, 0));
 
-        if !errors.is_empty() {
-            Err(errors)
-        } else {
-            Ok(tokens)
-        }
+        (tokens, errors)
     }
 }
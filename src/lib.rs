@@ -5,7 +5,14 @@ pub mod lexer;
 pub mod parser;
 pub mod interpreter;
 
-use crate::core::runtime::run_source;
+use crate::core::json::{
+    errors_to_json, outcomes_to_json, outcomes_to_typed_json_lines, tokens_to_json,
+};
+use crate::core::runtime::{
+    run_source, run_source_number, run_source_streaming, run_source_structured,
+    tokenize_source, Session,
+};
+use crate::interpreter::eval::AngleMode;
 
 #[wasm_bindgen]
 pub fn run_code(source: &str) -> String {
@@ -14,3 +21,84 @@ pub fn run_code(source: &str) -> String {
         Err(err) => format!("Error: {}", err),
     }
 }
+
+
+#[wasm_bindgen]
+pub fn run_code_number(source: &str) -> f64 {
+    run_source_number(source).unwrap_or(f64::NAN)
+}
+
+#[wasm_bindgen]
+pub fn run_code_values_json(source: &str) -> String {
+    match run_source_structured(source) {
+        Ok(outcomes) => outcomes_to_json(&outcomes),
+        Err(errors) => errors_to_json(&errors),
+    }
+}
+
+#[wasm_bindgen]
+pub fn run_code_typed(source: &str) -> String {
+    match run_source_structured(source) {
+        Ok(outcomes) => outcomes_to_typed_json_lines(&outcomes),
+        Err(errors) => errors_to_json(&errors),
+    }
+}
+
+#[wasm_bindgen]
+pub fn tokenize_code(source: &str) -> String {
+    let (tokens, errors) = tokenize_source(source);
+    tokens_to_json(&tokens, &errors)
+}
+
+#[wasm_bindgen(js_name = Session)]
+pub struct WasmSession {
+    session: Session,
+}
+
+#[wasm_bindgen(js_class = Session)]
+impl WasmSession {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WasmSession {
+        WasmSession {
+            session: Session::new(),
+        }
+    }
+
+    pub fn eval(&mut self, source: &str) -> String {
+        match self.session.run(source) {
+            Ok(output) => output,
+            Err(err) => format!("Error: {}", err),
+        }
+    }
+
+    // "deg" or "rad", the same names as `anglemode` in the language
+    #[wasm_bindgen(js_name = setAngleMode)]
+    pub fn set_angle_mode(&mut self, mode: &str) -> Result<(), String> {
+        let mode = AngleMode::from_name(mode)
+            .ok_or_else(|| format!("unknown angle mode '{}'", mode))?;
+        self.session.set_angle_mode(mode);
+        Ok(())
+    }
+}
+
+impl Default for WasmSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+extern "C" {
+    pub type OutputCallback;
+
+    #[wasm_bindgen(method, js_name = call)]
+    fn call(this: &OutputCallback, context: &JsValue, line: &str);
+}
+
+#[wasm_bindgen]
+pub fn run_code_streaming(source: &str, callback: &OutputCallback) {
+    let result = run_source_streaming(source, |line| callback.call(&JsValue::NULL, line));
+    if let Err(err) = result {
+        callback.call(&JsValue::NULL, &format!("Error: {}", err));
+    }
+}
@@ -1,23 +1,66 @@
-use std::fs;
+use std::{
+    env, fs,
+    io::{self, Read},
+    process, thread,
+};
 
-mod interpreter;
-mod lexer;
-mod parser;
-
-
-use crate::interpreter::eval::Evaluator;
-use crate::interpreter::value::Value;
-use crate::lexer::tokenizer::Lexer;
-use crate::parser::pratt::Parser;
+use spemath::interpreter::eval::{AngleMode, Evaluator};
+use spemath::interpreter::value::Value;
+use spemath::lexer::tokenizer::Lexer;
+use spemath::parser::ast::Expr;
+use spemath::parser::pratt::Parser;
 
+// Debug builds need tens of KiB of stack per nested call or nesting level, so
+// scripts run with enough for the parser's and the evaluator's depth limits
+const STACK_SIZE: usize = 64 * 1024 * 1024;
 
 fn main() {
     env_logger::init();
 
-    let source = fs::read_to_string("input.spemath")
-        .expect("Could not read input.spemath");
+    // `spemath-cli [--degrees] [--check] script.spemath`, or the script on stdin without a path
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let mut take_flag = |flag: &str| match args.iter().position(|arg| arg == flag) {
+        Some(index) => {
+            args.remove(index);
+            true
+        }
+        None => false,
+    };
+    let angle_mode = if take_flag("--degrees") {
+        AngleMode::Degrees
+    } else {
+        AngleMode::Radians
+    };
+    // Warn about definitions that use names not defined yet
+    let check_definitions = take_flag("--check");
+    let source = match args.into_iter().next() {
+        Some(path) => fs::read_to_string(&path).map_err(|err| (path, err)),
+        None => {
+            let mut source = String::new();
+            io::stdin()
+                .read_to_string(&mut source)
+                .map(|_| source)
+                .map_err(|err| ("<stdin>".to_string(), err))
+        }
+    };
+    let source = match source {
+        Ok(source) => source,
+        Err((path, err)) => {
+            eprintln!("Error: could not read {}: {}", path, err);
+            process::exit(1);
+        }
+    };
+
+    thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || run(&source, angle_mode, check_definitions))
+        .unwrap()
+        .join()
+        .unwrap();
+}
 
-    let mut lexer = Lexer::new(&source);
+fn run(source: &str, angle_mode: AngleMode, check_definitions: bool) {
+    let mut lexer = Lexer::new(source);
     let tokens = match lexer.tokenize() {
         Ok(tokens) => tokens,
         Err(errors) => {
@@ -39,11 +82,22 @@ fn main() {
         }
     };
 
+    evaluate(exprs, angle_mode, check_definitions);
+}
+
+fn evaluate(exprs: Vec<Expr>, angle_mode: AngleMode, check_definitions: bool) {
     let mut evaluator = Evaluator::new();
+    evaluator.angle_mode = angle_mode;
+    evaluator.check_definitions = check_definitions;
     for expr in exprs {
-        match evaluator.eval(&expr) {
+        let result = evaluator.eval(&expr);
+        for warning in evaluator.warnings.drain(..) {
+            eprintln!("Warning: {}", warning);
+        }
+        match result {
             Ok(Value::Unit) => {}
-            Ok(value) => println!("{:?}", value),
+            Ok(_) if matches!(expr, Expr::Assignment { .. }) => {}
+            Ok(value) => println!("{}", value),
             Err(err) => eprintln!("Evaluation error: {}", err),
         }
     }
@@ -1,12 +1,31 @@
-use crate::lexer::token::Token;
+use std::fmt;
+
+use crate::{
+    lexer::token::{Span, Token},
+    parser::symbol::Symbol,
+};
 
 #[allow(dead_code)]
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Expr {
     Number(f64),
-    Identifier(String),
+    Integer(i64),
+    String(String),
+    Boolean(bool),
+    Identifier(Symbol),
+    List(Vec<Expr>),
+    Block(Vec<Expr>),
     Assignment {
-        target: String,
+        target: Symbol,
+        value: Box<Expr>
+    },
+    Constant {
+        target: Symbol,
+        value: Box<Expr>
+    },
+    DefaultAssignment {
+        target: Symbol,
         value: Box<Expr>
     },
     Binary {
@@ -18,13 +37,167 @@ pub enum Expr {
         op: Token,
         expr: Box<Expr>
     },
+    Postfix {
+        op: Token,
+        expr: Box<Expr>
+    },
+    If {
+        cond: Box<Expr>,
+        then_branch: Box<Expr>,
+        else_branch: Box<Expr>,
+    },
     Call {
         function: Box<Expr>,
         args: Vec<Expr>
     },
+    Index {
+        target: Box<Expr>,
+        index: Box<Expr>,
+    },
+    Range {
+        start: Box<Expr>,
+        end: Box<Expr>,
+    },
     Function {
-        name: String,
-        args: Vec<String>,
+        name: Symbol,
+        args: Vec<Symbol>,
         body: Box<Expr>,
+    },
+    Lambda {
+        param: Symbol,
+        body: Box<Expr>,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub expr: Expr,
+    pub span: Span,
+    pub len: usize,
+}
+
+impl Expr {
+    fn is_compound(&self) -> bool {
+        // Bars already delimit their operand
+        if let Expr::Unary { op: Token::Pipe, .. } = self {
+            return false;
+        }
+        matches!(
+            self,
+            Expr::Assignment { .. }
+                | Expr::Constant { .. }
+                | Expr::DefaultAssignment { .. }
+                | Expr::Binary { .. }
+                | Expr::Range { .. }
+                | Expr::Unary { .. }
+                | Expr::Postfix { .. }
+                | Expr::If { .. }
+                | Expr::Function { .. }
+                | Expr::Lambda { .. }
+        )
+    }
+
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_compound() {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+fn fmt_list(items: &[impl fmt::Display], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            // Whole floats keep their `.0`, so they read back as floats
+            Expr::Number(n) => write!(f, "{:?}", n),
+            Expr::Integer(n) => write!(f, "{}", n),
+            Expr::String(s) => write!(f, "{:?}", s),
+            Expr::Boolean(b) => write!(f, "{}", b),
+            Expr::Identifier(name) => write!(f, "{}", name),
+            Expr::List(items) => {
+                write!(f, "[")?;
+                fmt_list(items, f)?;
+                write!(f, "]")
+            }
+            Expr::Block(exprs) => {
+                // Parentheses around a single expression would only group it
+                let (open, close) = if exprs.len() < 2 { ("{", "}") } else { ("(", ")") };
+                write!(f, "{}", open)?;
+                for (i, expr) in exprs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{}", expr)?;
+                }
+                write!(f, "{}", close)
+            }
+            Expr::Assignment { target, value } => write!(f, "{} = {}", target, value),
+            Expr::Constant { target, value } => write!(f, "const {} = {}", target, value),
+            Expr::DefaultAssignment { target, value } => write!(f, "{} ?= {}", target, value),
+            Expr::Binary { left, op, right } => {
+                left.fmt_operand(f)?;
+                write!(f, " {} ", op)?;
+                right.fmt_operand(f)
+            }
+            Expr::Unary {
+                op: Token::Pipe,
+                expr,
+            } => write!(f, "|{}|", expr),
+            Expr::Unary { op, expr } => {
+                // `x not in xs` parses to a negated `in`, written with the prefix `!`
+                match op {
+                    Token::Not => write!(f, "!")?,
+                    _ => write!(f, "{}", op)?,
+                }
+                expr.fmt_operand(f)
+            }
+            Expr::Postfix { op, expr } => {
+                expr.fmt_operand(f)?;
+                write!(f, "{}", op)
+            }
+            Expr::If {
+                cond,
+                then_branch,
+                else_branch,
+            } => {
+                cond.fmt_operand(f)?;
+                write!(f, " ? ")?;
+                then_branch.fmt_operand(f)?;
+                write!(f, " : ")?;
+                else_branch.fmt_operand(f)
+            }
+            Expr::Call { function, args } => {
+                function.fmt_operand(f)?;
+                write!(f, "(")?;
+                fmt_list(args, f)?;
+                write!(f, ")")
+            }
+            Expr::Index { target, index } => {
+                target.fmt_operand(f)?;
+                write!(f, "[{}]", index)
+            }
+            Expr::Range { start, end } => {
+                start.fmt_operand(f)?;
+                write!(f, "..")?;
+                end.fmt_operand(f)
+            }
+            Expr::Function { name, args, body } => {
+                write!(f, "{}(", name)?;
+                fmt_list(args, f)?;
+                write!(f, ") = {}", body)
+            }
+            Expr::Lambda { param, body } => write!(f, "{} -> {}", param, body),
+        }
     }
 }
@@ -0,0 +1,82 @@
+// Matches (), [] and {} before parsing, so one missing bracket is reported once
+// instead of as every error the parser runs into afterwards
+use crate::{
+    lexer::token::{Span, SpannedToken, Token},
+    parser::error::ParserError,
+};
+
+fn bracket(token: &Token) -> Option<char> {
+    match token {
+        Token::LParen => Some('('),
+        Token::RParen => Some(')'),
+        Token::LBracket => Some('['),
+        Token::RBracket => Some(']'),
+        Token::LBrace => Some('{'),
+        Token::RBrace => Some('}'),
+        _ => None,
+    }
+}
+
+fn closer(open: char) -> Option<char> {
+    match open {
+        '(' => Some(')'),
+        '[' => Some(']'),
+        '{' => Some('}'),
+        _ => None,
+    }
+}
+
+pub fn check_brackets(tokens: &[SpannedToken]) -> Result<(), ParserError> {
+    let mut open: Vec<(char, &Span)> = Vec::new();
+
+    for token in tokens {
+        let Some(c) = bracket(&token.value) else {
+            continue;
+        };
+        if closer(c).is_some() {
+            open.push((c, &token.span));
+            continue;
+        }
+
+        let span = &token.span;
+        match open.pop() {
+            Some((opener, _)) if closer(opener) == Some(c) => {}
+            Some((opener, open_span)) => {
+                return Err(ParserError::MismatchedBracket {
+                    open: opener,
+                    open_line: open_span.line,
+                    open_col: open_span.col,
+                    found: c,
+                    line: span.line,
+                    col: span.col,
+                    pos: span.pos,
+                });
+            }
+            None => {
+                return Err(ParserError::UnexpectedToken {
+                    found: token.value.clone(),
+                    line: span.line,
+                    col: span.col,
+                    pos: span.pos,
+                    len: span.len,
+                });
+            }
+        }
+    }
+
+    // The innermost unclosed bracket is the one most likely missing its closer
+    match open.pop() {
+        Some((opener, span)) => Err(ParserError::UnclosedBracket {
+            open: opener,
+            line: span.line,
+            col: span.col,
+            pos: span.pos,
+        }),
+        None => Ok(()),
+    }
+}
+
+// The bracket that closes `open`, for messages
+pub fn closing(open: &char) -> char {
+    closer(*open).unwrap_or(*open)
+}
@@ -1,6 +1,9 @@
 use thiserror::Error;
 
-use crate::{lexer::token::Token, parser::ast::Expr};
+use crate::{
+    lexer::token::Token,
+    parser::{ast::Expr, brackets::closing},
+};
 
 #[derive(Error, Debug, Clone)]
 pub enum ParserError {
@@ -10,6 +13,7 @@ pub enum ParserError {
         line: usize,
         col: usize,
         pos: usize,
+        len: usize,
     },
 
     #[error("line {line}, col {col}: Expected {}, found {}", expected.description(), found.description())]
@@ -19,6 +23,7 @@ pub enum ParserError {
         line: usize,
         col: usize,
         pos: usize,
+        len: usize,
     },
 
     #[error("line {line}, col {col}: Unexpected end of input, expected '{expected}'")]
@@ -29,16 +34,27 @@ pub enum ParserError {
         pos: usize,
     },
 
+    #[error("line {line}, col {col}: '(' of this call is never closed, expected ')'")]
+    UnclosedCall { line: usize, col: usize, pos: usize },
+
     #[error(
-        "line {line}, col {col}: Cannot assign to '{target:?}', left-hand side must be a variable"
+        "line {line}, col {col}: Cannot assign to '{target}', left-hand side must be a variable{}",
+        if *.in_condition { " (did you mean '=='?)" } else { "" }
     )]
     InvalidAssignment {
         target: Expr,
+        // Inside an `if` condition, where the hint to use '==' applies
+        in_condition: bool,
         line: usize,
         col: usize,
         pos: usize,
     },
 
+    #[error(
+        "line {line}, col {col}: 'const' must be followed by an assignment like 'const g = 9.81'"
+    )]
+    InvalidConstant { line: usize, col: usize, pos: usize },
+
     #[error("line {line}, col {col}: Function parameter must be an identifier, found '{param:?}'")]
     InvalidFunctionParameter {
         param: Expr,
@@ -49,4 +65,106 @@ pub enum ParserError {
 
     #[error("line {line}, col {col}: Invalid function definition syntax")]
     InvalidFunctionDefinition { line: usize, col: usize, pos: usize },
+
+    #[error("line {line}, col {col}: Assignment used as a condition (did you mean '=='?)")]
+    AssignmentInCondition { line: usize, col: usize, pos: usize },
+
+    #[error(
+        "line {line}, col {col}: Expected '{}' to close '{open}' from line {open_line}, col {open_col}, found '{found}'",
+        closing(open)
+    )]
+    MismatchedBracket {
+        open: char,
+        open_line: usize,
+        open_col: usize,
+        found: char,
+        line: usize,
+        col: usize,
+        pos: usize,
+    },
+
+    #[error("line {line}, col {col}: '{open}' is never closed")]
+    UnclosedBracket {
+        open: char,
+        line: usize,
+        col: usize,
+        pos: usize,
+    },
+
+    #[error("line {line}, col {col}: Expression is nested more than {limit} levels deep")]
+    TooDeeplyNested {
+        limit: usize,
+        line: usize,
+        col: usize,
+        pos: usize,
+    },
+
+    #[error(
+        "line {line}, col {col}: Expression is more than {limit} operations deep, split it into smaller steps"
+    )]
+    ExpressionTooDeep {
+        limit: usize,
+        line: usize,
+        col: usize,
+        pos: usize,
+    },
+
+    #[error("line {line}, col {col}: Too many distinct names (more than {limit} bytes in total)")]
+    TooManyNames {
+        limit: usize,
+        line: usize,
+        col: usize,
+        pos: usize,
+        len: usize,
+    },
+
+    #[error("line {line}, col {col}: Numbers separated only by a space (use '*' to multiply)")]
+    AdjacentNumbers {
+        line: usize,
+        col: usize,
+        pos: usize,
+        len: usize,
+    },
+}
+
+impl ParserError {
+    pub fn location(&self) -> (usize, usize, usize) {
+        match self {
+            ParserError::UnexpectedToken { line, col, pos, .. }
+            | ParserError::ExpectedToken { line, col, pos, .. }
+            | ParserError::UnexpectedEof { line, col, pos, .. }
+            | ParserError::UnclosedCall { line, col, pos }
+            | ParserError::InvalidAssignment { line, col, pos, .. }
+            | ParserError::InvalidConstant { line, col, pos }
+            | ParserError::InvalidFunctionParameter { line, col, pos, .. }
+            | ParserError::InvalidFunctionDefinition { line, col, pos }
+            | ParserError::AssignmentInCondition { line, col, pos }
+            | ParserError::AdjacentNumbers { line, col, pos, .. }
+            | ParserError::MismatchedBracket { line, col, pos, .. }
+            | ParserError::UnclosedBracket { line, col, pos, .. }
+            | ParserError::TooDeeplyNested { line, col, pos, .. }
+            | ParserError::ExpressionTooDeep { line, col, pos, .. }
+            | ParserError::TooManyNames { line, col, pos, .. } => (*line, *col, *pos),
+        }
+    }
+
+    pub fn span_len(&self) -> usize {
+        match self {
+            ParserError::UnexpectedToken { len, .. }
+            | ParserError::ExpectedToken { len, .. }
+            | ParserError::AdjacentNumbers { len, .. }
+            | ParserError::TooManyNames { len, .. } => *len,
+            ParserError::UnexpectedEof { .. } => 0,
+            ParserError::InvalidConstant { .. } => Token::Const.to_string().len(),
+            ParserError::UnclosedCall { .. }
+            | ParserError::MismatchedBracket { .. }
+            | ParserError::UnclosedBracket { .. }
+            | ParserError::InvalidAssignment { .. }
+            | ParserError::InvalidFunctionParameter { .. }
+            | ParserError::InvalidFunctionDefinition { .. }
+            | ParserError::AssignmentInCondition { .. }
+            | ParserError::TooDeeplyNested { .. }
+            | ParserError::ExpressionTooDeep { .. } => 1,
+        }
+    }
 }
@@ -1,4 +1,6 @@
 pub mod ast;
+pub mod brackets;
 pub mod pratt;
 pub mod error;
+pub mod symbol;
 pub mod tests;
\ No newline at end of file
@@ -1,41 +1,89 @@
 use crate::lexer::token::SpannedToken;
 use crate::parser::error::ParserError;
-use crate::{lexer::token::Token, parser::ast::Expr};
+use crate::{
+    lexer::token::Token,
+    parser::{
+        ast::{Expr, Statement},
+        symbol::{MAX_INTERNED_BYTES, Symbol},
+    },
+};
 
 #[derive(PartialOrd, Ord, PartialEq, Eq, Debug, Clone, Copy)]
 enum Precedence {
     Lowest = 0,
     Assignment = 1,
-    Comparison = 2,
-    Sum = 3,
-    Product = 4,
-    Power = 5,
-    Prefix = 6,
-    Call = 7,
+    Lambda = 2,
+    Ternary = 3,
+    Or = 4,
+    And = 5,
+    Comparison = 6,
+    Range = 7,
+    Sum = 8,
+    Product = 9,
+    Power = 10,
+    Prefix = 11,
+    Postfix = 12,
+    Call = 13,
 }
 
 impl Precedence {
     fn from_token(token: &Token) -> Precedence {
         match token {
             Token::Equal => Precedence::Assignment,
+            Token::Question => Precedence::Ternary,
+            Token::Or | Token::PipePipe => Precedence::Or,
+            Token::And | Token::AmpAmp => Precedence::And,
+            Token::DotDot => Precedence::Range,
             Token::Plus | Token::Minus => Precedence::Sum,
-            Token::Star | Token::Slash | Token::Percent => Precedence::Product,
+            Token::Star | Token::Slash | Token::SlashSlash | Token::Percent => Precedence::Product,
             Token::Caret => Precedence::Power,
             Token::EqualEqual
             | Token::ExclamationEqual
             | Token::Less
             | Token::Greater
             | Token::LessEqual
-            | Token::GreaterEqual => Precedence::Comparison,
+            | Token::GreaterEqual
+            | Token::In => Precedence::Comparison,
             Token::LParen => Precedence::Call,
             _ => Precedence::Lowest,
         }
     }
 }
 
+// How deeply expressions may nest inside parentheses, brackets, braces, call
+// arguments and prefix operators. Parsing recurses once per level, so anything
+// deeper could overflow the stack instead of failing with an error.
+pub const MAX_NESTING_DEPTH: usize = 128;
+
+// How deep the syntax tree may grow, counting each operator of a chain like
+// `1 + 2 + 3` as a level. Evaluating a tree recurses once per level.
+pub const MAX_TREE_DEPTH: usize = 512;
+
+// What `2 3`, two numbers separated only by spaces, means
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AdjacentNumbers {
+    // Two expressions, as if a newline separated them
+    #[default]
+    Separate,
+    // `2 * 3`, like other implicit products
+    Multiply,
+    // Rejected, since either reading may be a typo
+    Error,
+}
+
 pub struct Parser {
     tokens: Vec<SpannedToken>,
     pos: usize,
+    paren_depth: usize,
+    // Absolute value bars opened and not yet closed; inside them, `|` after an operand closes
+    abs_depth: usize,
+    // Levels the expression being parsed may already have, see MAX_NESTING_DEPTH
+    depth: usize,
+    // Deepest tree level reached below the current expression, see MAX_TREE_DEPTH
+    peak: usize,
+    // Parsing the condition of an `if`, where a stray `=` was probably meant as `==`
+    in_condition: bool,
+    adjacent_numbers: AdjacentNumbers,
     errors: Vec<ParserError>,
 }
 
@@ -45,10 +93,21 @@ impl Parser {
         Parser {
             tokens,
             pos: 0,
+            paren_depth: 0,
+            abs_depth: 0,
+            depth: 0,
+            peak: 0,
+            in_condition: false,
+            adjacent_numbers: AdjacentNumbers::default(),
             errors: Vec::new(),
         }
     }
 
+    pub fn with_adjacent_numbers(mut self, policy: AdjacentNumbers) -> Self {
+        self.adjacent_numbers = policy;
+        self
+    }
+
     fn position(&self) -> (usize, usize, usize) {
         if let Some(spanned) = self.tokens.get(self.pos) {
             (spanned.span.line, spanned.span.col, spanned.span.pos)
@@ -59,6 +118,27 @@ impl Parser {
         }
     }
 
+    fn current_len(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map_or(0, |spanned| spanned.span.len)
+    }
+
+    // Counts `levels` more nesting for the expression being parsed
+    fn nest(&mut self, levels: usize) -> Result<(), ParserError> {
+        self.depth += levels;
+        if self.depth > MAX_NESTING_DEPTH {
+            let (line, col, pos) = self.position();
+            return Err(ParserError::TooDeeplyNested {
+                limit: MAX_NESTING_DEPTH,
+                line,
+                col,
+                pos,
+            });
+        }
+        Ok(())
+    }
+
     fn error(&mut self, error: ParserError) {
         self.errors.push(error);
     }
@@ -67,10 +147,10 @@ impl Parser {
         let mut i = self.pos;
         while i > 0 {
             i -= 1;
-            if let Some(spanned) = self.tokens.get(i) {
-                if !matches!(spanned.value, Token::Whitespace) {
-                    return Some(&spanned.value);
-                }
+            if let Some(spanned) = self.tokens.get(i)
+                && !matches!(spanned.value, Token::Whitespace)
+            {
+                return Some(&spanned.value);
             }
         }
         None
@@ -85,6 +165,8 @@ impl Parser {
     }
 
     fn synchronize(&mut self) {
+        self.paren_depth = 0;
+        self.abs_depth = 0;
         while let Some(token) = self.current() {
             match token {
                 Token::Semicolon | Token::Newline | Token::Eof => {
@@ -136,28 +218,45 @@ impl Parser {
                     line,
                     col,
                     pos,
+                    len: self.current_len(),
                 })
             }
         }
     }
 
     fn whitespace(&mut self) {
-        while let Some(Token::Whitespace) = self.current() {
+        while let Some(token) = self.current() {
+            match token {
+                Token::Whitespace => self.advance(),
+                Token::Newline if self.paren_depth > 0 => self.advance(),
+                _ => break,
+            }
+        }
+    }
+
+    // Skips blank lines as well as spaces
+    fn line_breaks(&mut self) {
+        while let Some(Token::Whitespace | Token::Newline) = self.current() {
             self.advance();
         }
     }
 
     pub fn parse(&mut self) -> Result<Vec<Expr>, Vec<ParserError>> {
-        let exprs = self.program();
+        self.parse_statements()
+            .map(|statements| statements.into_iter().map(|s| s.expr).collect())
+    }
+
+    pub fn parse_statements(&mut self) -> Result<Vec<Statement>, Vec<ParserError>> {
+        let statements = self.program();
 
         if self.errors.is_empty() {
-            Ok(exprs)
+            Ok(statements)
         } else {
             Err(self.errors.clone())
         }
     }
 
-    fn program(&mut self) -> Vec<Expr> {
+    fn program(&mut self) -> Vec<Statement> {
         let mut nodes = Vec::new();
 
         while let Some(tok) = self.current().cloned() {
@@ -166,21 +265,52 @@ impl Parser {
                 Token::Newline | Token::Semicolon | Token::Whitespace => {
                     self.advance();
                 }
-                _ => match self.expression(Precedence::Lowest) {
-                    Ok(expr) => nodes.push(expr),
-                    Err(err) => {
-                        self.error(err);
-                        self.synchronize();
+                _ => {
+                    let span = self.tokens[self.pos].span.clone();
+                    self.depth = 0;
+                    self.peak = 0;
+                    self.in_condition = false;
+                    match self.expression(Precedence::Lowest) {
+                        Ok(expr) => {
+                            let len = self.statement_end() - span.pos;
+                            nodes.push(Statement { expr, span, len });
+                        }
+                        Err(err) => {
+                            self.error(err);
+                            self.synchronize();
+                        }
                     }
-                },
+                }
             }
         }
 
         nodes
     }
 
+    fn statement_end(&self) -> usize {
+        let mut end = self.pos;
+        while end > 0 && matches!(self.tokens[end - 1].value, Token::Whitespace) {
+            end -= 1;
+        }
+        self.position_of(end)
+    }
+
+    fn position_of(&self, index: usize) -> usize {
+        match self.tokens.get(index).or(self.tokens.last()) {
+            Some(spanned) => spanned.span.pos,
+            None => 0,
+        }
+    }
+
     fn expression(&mut self, precedence: Precedence) -> Result<Expr, ParserError> {
-        let mut left = self.prefix()?;
+        let outer_depth = self.depth;
+        let outer_peak = self.peak;
+        self.nest(1)?;
+        // The root of this expression's tree is at level `base`
+        let base = self.depth;
+        self.peak = base;
+        let mut left = self.prefix(precedence)?;
+        let mut height = self.peak - base + 1;
 
         log::debug!(
             "expression() at pos {}, precedence {:?}",
@@ -194,6 +324,8 @@ impl Parser {
             let Some(token) = self.current().cloned() else {
                 break;
             };
+            // Operands parsed by this step raise `peak` to their deepest level
+            self.peak = base;
 
             match token {
                 Token::Eof | Token::Newline | Token::Semicolon => {
@@ -201,6 +333,20 @@ impl Parser {
                     break;
                 }
 
+                // Closing bars, left for the bar that opened them to consume
+                Token::Pipe if self.abs_depth > 0 => break,
+                Token::PipePipe if self.abs_depth > 1 => break,
+                Token::Pipe if !self.is_implicit_multiplication(&token) => {
+                    let (line, col, pos) = self.position();
+                    return Err(ParserError::UnexpectedToken {
+                        found: token,
+                        line,
+                        col,
+                        pos,
+                        len: self.current_len(),
+                    });
+                }
+
                 Token::Equal => {
                     log::debug!("expression() found assignment operator at pos {}", self.pos);
                     let token_prec = Precedence::Assignment;
@@ -238,7 +384,8 @@ impl Parser {
                                 }
 
                                 self.advance();
-                                let body = self.expression(Precedence::Assignment)?;
+                                // `f(x) = g = x` defines f with the body `g = x`
+                                let body = self.expression(Precedence::Lowest)?;
                                 left = Expr::Function {
                                     name,
                                     args: params,
@@ -264,7 +411,8 @@ impl Parser {
                                 self.pos
                             );
                             self.advance();
-                            let value = self.expression(Precedence::Assignment)?;
+                            // Lowest lets `a = b = 5` nest to the right
+                            let value = self.expression(Precedence::Lowest)?;
                             left = Expr::Assignment {
                                 target: name,
                                 value: Box::new(value),
@@ -279,6 +427,7 @@ impl Parser {
 
                             return Err(ParserError::InvalidAssignment {
                                 target: left,
+                                in_condition: self.in_condition,
                                 line,
                                 col,
                                 pos,
@@ -287,12 +436,103 @@ impl Parser {
                     }
                 }
 
+                Token::Arrow => {
+                    log::debug!("expression() found lambda at pos {}", self.pos);
+                    if Precedence::Lambda <= precedence {
+                        break;
+                    }
+
+                    let Expr::Identifier(param) = left else {
+                        log::warn!("expression() invalid lambda parameter at pos {}", self.pos);
+                        let (line, col, pos) = self.position();
+                        return Err(ParserError::InvalidFunctionParameter {
+                            param: left,
+                            line,
+                            col,
+                            pos,
+                        });
+                    };
+
+                    self.advance();
+                    let body = self.expression(Precedence::Assignment)?;
+                    left = Expr::Lambda {
+                        param,
+                        body: Box::new(body),
+                    };
+                }
+
+                Token::QuestionEqual => {
+                    log::debug!("expression() found default assignment at pos {}", self.pos);
+                    if Precedence::Assignment <= precedence {
+                        break;
+                    }
+
+                    let Expr::Identifier(name) = left else {
+                        log::warn!(
+                            "expression() invalid default assignment target at pos {}",
+                            self.pos
+                        );
+                        let (line, col, pos) = self.position();
+                        return Err(ParserError::InvalidAssignment {
+                            target: left,
+                            in_condition: false,
+                            line,
+                            col,
+                            pos,
+                        });
+                    };
+
+                    self.advance();
+                    let value = self.expression(Precedence::Assignment)?;
+                    left = Expr::DefaultAssignment {
+                        target: name,
+                        value: Box::new(value),
+                    };
+                }
+
+                Token::PlusEqual | Token::MinusEqual | Token::StarEqual | Token::SlashEqual => {
+                    log::debug!("expression() found compound assignment at pos {}", self.pos);
+                    if Precedence::Assignment <= precedence {
+                        break;
+                    }
+
+                    let Expr::Identifier(name) = left else {
+                        log::warn!(
+                            "expression() invalid compound assignment target at pos {}",
+                            self.pos
+                        );
+                        let (line, col, pos) = self.position();
+                        return Err(ParserError::InvalidAssignment {
+                            target: left,
+                            in_condition: false,
+                            line,
+                            col,
+                            pos,
+                        });
+                    };
+
+                    // `x += v` is evaluated as `x = x + (v)`
+                    let op = match token {
+                        Token::PlusEqual => Token::Plus,
+                        Token::MinusEqual => Token::Minus,
+                        Token::StarEqual => Token::Star,
+                        _ => Token::Slash,
+                    };
+                    self.advance();
+                    let value = self.expression(Precedence::Lowest)?;
+                    left = Expr::Assignment {
+                        target: name,
+                        value: Box::new(Expr::Binary {
+                            op,
+                            left: Box::new(Expr::Identifier(name)),
+                            right: Box::new(value),
+                        }),
+                    };
+                }
+
                 Token::LParen => {
                     log::debug!("expression() found '(' at pos {}", self.pos);
-                    if matches!(left, Expr::Identifier(_))
-                        && !self.has_whitespace_before()
-                        && precedence < Precedence::Product
-                    {
+                    if matches!(left, Expr::Identifier(_)) && self.is_call() {
                         log::debug!("expression() found function call at pos {}", self.pos);
                         left = self.call(left)?;
                     } else if !self.has_whitespace_before() {
@@ -312,6 +552,92 @@ impl Parser {
                     }
                 }
 
+                Token::LBracket if !self.has_whitespace_before() => {
+                    log::debug!("expression() found index at pos {}", self.pos);
+                    if Precedence::Call <= precedence {
+                        break;
+                    }
+                    left = self.index(left)?;
+                }
+
+                Token::Exclamation => {
+                    log::debug!("expression() found factorial at pos {}", self.pos);
+                    if Precedence::Postfix <= precedence {
+                        break;
+                    }
+
+                    self.advance();
+                    left = Expr::Postfix {
+                        op: Token::Exclamation,
+                        expr: Box::new(left),
+                    };
+                }
+
+                // `50%` is 0.5, `7 % 3` is a remainder
+                Token::Percent if !self.has_operand_after() => {
+                    log::debug!("expression() found percent at pos {}", self.pos);
+                    if Precedence::Postfix <= precedence {
+                        break;
+                    }
+
+                    self.advance();
+                    left = Expr::Postfix {
+                        op: Token::Percent,
+                        expr: Box::new(left),
+                    };
+                }
+
+                Token::Question => {
+                    log::debug!("expression() found conditional at pos {}", self.pos);
+                    let token_prec = Precedence::Ternary;
+                    if token_prec <= precedence {
+                        break;
+                    }
+
+                    self.advance();
+                    let then_branch = self.expression(Precedence::Lowest)?;
+                    self.expect(&Token::Colon)?;
+                    let else_branch = self.expression(Precedence::Assignment)?;
+                    left = Expr::If {
+                        cond: Box::new(left),
+                        then_branch: Box::new(then_branch),
+                        else_branch: Box::new(else_branch),
+                    };
+                }
+
+                Token::Not => {
+                    log::debug!("expression() found 'not in' operator at pos {}", self.pos);
+                    let token_prec = Precedence::Comparison;
+                    if token_prec <= precedence {
+                        break;
+                    }
+
+                    self.advance();
+                    self.expect(&Token::In)?;
+
+                    let right = self.expression(token_prec)?;
+                    left = Expr::Unary {
+                        op: Token::Not,
+                        expr: Box::new(Expr::Binary {
+                            left: Box::new(left),
+                            op: Token::In,
+                            right: Box::new(right),
+                        }),
+                    };
+                }
+
+                Token::Number(_) | Token::Integer(_)
+                    if self.adjacent_numbers == AdjacentNumbers::Error && self.follows_number() =>
+                {
+                    let (line, col, pos) = self.position();
+                    return Err(ParserError::AdjacentNumbers {
+                        line,
+                        col,
+                        pos,
+                        len: self.current_len(),
+                    });
+                }
+
                 t if self.is_implicit_multiplication(&t) => {
                     log::debug!(
                         "expression() found implicit multiplication at pos {}",
@@ -350,20 +676,40 @@ impl Parser {
                     self.advance();
 
                     let right = self.expression(token_prec)?;
-                    left = Expr::Binary {
-                        left: Box::new(left),
-                        op: token,
-                        right: Box::new(right),
+                    left = match token {
+                        Token::DotDot => Expr::Range {
+                            start: Box::new(left),
+                            end: Box::new(right),
+                        },
+                        _ => Expr::Binary {
+                            left: Box::new(left),
+                            op: token,
+                            right: Box::new(right),
+                        },
                     };
                 }
             }
+
+            // Each step wraps `left` in a new root, above it and any new operand
+            height = 1 + height.max(self.peak - base);
+            if base + height - 1 > MAX_TREE_DEPTH {
+                let (line, col, pos) = self.position();
+                return Err(ParserError::ExpressionTooDeep {
+                    limit: MAX_TREE_DEPTH,
+                    line,
+                    col,
+                    pos,
+                });
+            }
         }
 
         log::debug!("expression() returning {:?}", left);
+        self.depth = outer_depth;
+        self.peak = outer_peak.max(base + height - 1);
         Ok(left)
     }
 
-    fn prefix(&mut self) -> Result<Expr, ParserError> {
+    fn prefix(&mut self, precedence: Precedence) -> Result<Expr, ParserError> {
         log::debug!("prefix() at pos {}", self.pos);
         self.whitespace();
 
@@ -374,32 +720,188 @@ impl Parser {
                 Ok(Expr::Number(n))
             }
 
+            Some(Token::Integer(n)) => {
+                log::debug!("prefix() found integer {:?}", n);
+                self.advance();
+                Ok(Expr::Integer(n))
+            }
+
+            Some(Token::String(s)) => {
+                log::debug!("prefix() found string {:?}", s);
+                self.advance();
+                Ok(Expr::String(s))
+            }
+
+            Some(Token::Boolean(b)) => {
+                log::debug!("prefix() found boolean {:?}", b);
+                self.advance();
+                Ok(Expr::Boolean(b))
+            }
+
             Some(Token::Identifier(name)) => {
                 log::debug!("prefix() found identifier {:?}", name);
+                let Some(symbol) = Symbol::try_intern(&name) else {
+                    let (line, col, pos) = self.position();
+                    return Err(ParserError::TooManyNames {
+                        limit: MAX_INTERNED_BYTES,
+                        line,
+                        col,
+                        pos,
+                        len: self.current_len(),
+                    });
+                };
                 self.advance();
-                Ok(Expr::Identifier(name))
+                Ok(Expr::Identifier(symbol))
             }
 
-            Some(Token::Minus) | Some(Token::Plus) => {
+            Some(Token::Minus) | Some(Token::Plus) | Some(Token::Exclamation) => {
                 log::debug!("prefix() found unary operator {:?}", self.current());
-                let op = self.current().cloned().unwrap();
-                self.advance();
-                let expr = self.expression(Precedence::Prefix)?;
-                Ok(Expr::Unary {
-                    op,
-                    expr: Box::new(expr),
-                })
+                // A chain like `--x` is collected in a loop rather than by recursion,
+                // so long chains cannot overflow the stack
+                let mut ops = Vec::new();
+                while let Some(op @ (Token::Minus | Token::Plus | Token::Exclamation)) =
+                    self.current().cloned()
+                {
+                    self.nest(1)?;
+                    ops.push(op);
+                    self.advance();
+                    self.whitespace();
+                }
+                let mut expr = self.expression(Precedence::Prefix)?;
+
+                for op in ops.into_iter().rev() {
+                    // A sign covers the whole implicit product: `-2x` is `-(2 * x)`. Not
+                    // in an exponent, which stops before one: `3^-2x` is `(3^-2) * x`,
+                    // like `3^2x`
+                    while op != Token::Exclamation
+                        && precedence < Precedence::Power
+                        && let Some(token) = self.current().cloned()
+                        && self.is_implicit_multiplication(&token)
+                    {
+                        log::debug!("prefix() extending sign over implicit product");
+                        let right = self.expression(Precedence::Product)?;
+                        expr = Expr::Binary {
+                            left: Box::new(expr),
+                            op: Token::Star,
+                            right: Box::new(right),
+                        };
+                    }
+
+                    expr = Expr::Unary {
+                        op,
+                        expr: Box::new(expr),
+                    };
+                }
+                Ok(expr)
             }
 
             Some(Token::LParen) => {
                 log::debug!("prefix() found grouped expression");
                 self.advance();
-                let expr = self.expression(Precedence::Lowest)?;
+                self.paren_depth += 1;
+                let mut exprs = vec![self.expression(Precedence::Lowest)?];
+                // `(a; b)` is a block evaluating to its last expression
+                while matches!(self.current(), Some(Token::Semicolon)) {
+                    self.advance();
+                    exprs.push(self.expression(Precedence::Lowest)?);
+                }
                 self.expect(&Token::RParen)?;
+                self.paren_depth -= 1;
+
+                let expr = if exprs.len() == 1 {
+                    exprs.remove(0)
+                } else {
+                    Expr::Block(exprs)
+                };
                 log::debug!("prefix() done grouping expression: {:?}", expr);
                 Ok(expr)
             }
 
+            Some(Token::LBrace) => {
+                log::debug!("prefix() found braced block");
+                self.advance();
+                // Statements inside end at a newline or `;`, as they do at the top level
+                let outer_paren_depth = std::mem::replace(&mut self.paren_depth, 0);
+                let outer_abs_depth = std::mem::replace(&mut self.abs_depth, 0);
+                // Always a block, even with one expression, so its bindings stay local
+                let mut exprs = Vec::new();
+                loop {
+                    self.line_breaks();
+                    if matches!(self.current(), Some(Token::RBrace)) {
+                        break;
+                    }
+                    exprs.push(self.expression(Precedence::Lowest)?);
+                    self.whitespace();
+                    match self.current() {
+                        Some(Token::Semicolon | Token::Newline) => self.advance(),
+                        _ => break,
+                    }
+                }
+                self.line_breaks();
+                self.expect(&Token::RBrace)?;
+                self.paren_depth = outer_paren_depth;
+                self.abs_depth = outer_abs_depth;
+                log::debug!("prefix() done with braced block: {:?}", exprs);
+                Ok(Expr::Block(exprs))
+            }
+
+            // `||x| - 1|` starts with two opening bars, not `or`
+            Some(Token::Pipe | Token::PipePipe) => {
+                log::debug!("prefix() found absolute value bars");
+                self.split_pipes();
+                self.advance();
+                self.abs_depth += 1;
+                let expr = self.expression(Precedence::Lowest)?;
+                self.whitespace();
+                self.split_pipes();
+                self.expect(&Token::Pipe)?;
+                self.abs_depth -= 1;
+                Ok(Expr::Unary {
+                    op: Token::Pipe,
+                    expr: Box::new(expr),
+                })
+            }
+
+            Some(Token::Const) => {
+                log::debug!("prefix() found constant definition");
+                let (line, col, pos) = self.position();
+                self.advance();
+                match self.expression(Precedence::Lowest)? {
+                    Expr::Assignment { target, value } => Ok(Expr::Constant { target, value }),
+                    _ => Err(ParserError::InvalidConstant { line, col, pos }),
+                }
+            }
+
+            Some(Token::If) => {
+                log::debug!("prefix() found if expression");
+                self.advance();
+                self.whitespace();
+                let (line, col, pos) = self.position();
+
+                let outer_condition = std::mem::replace(&mut self.in_condition, true);
+                let cond = self.expression(Precedence::Lowest);
+                self.in_condition = outer_condition;
+                let cond = cond?;
+                if matches!(cond, Expr::Assignment { .. }) {
+                    return Err(ParserError::AssignmentInCondition { line, col, pos });
+                }
+                self.expect(&Token::Then)?;
+                let then_branch = self.expression(Precedence::Lowest)?;
+                self.expect(&Token::Else)?;
+                let else_branch = self.expression(Precedence::Assignment)?;
+
+                Ok(Expr::If {
+                    cond: Box::new(cond),
+                    then_branch: Box::new(then_branch),
+                    else_branch: Box::new(else_branch),
+                })
+            }
+
+            Some(Token::LBracket) => {
+                log::debug!("prefix() found list literal");
+                self.list()
+            }
+
             Some(token) => {
                 log::warn!("prefix() found unexpected token {:?}", token);
                 let (line, col, pos) = self.position();
@@ -408,6 +910,7 @@ impl Parser {
                     line,
                     col,
                     pos,
+                    len: self.current_len(),
                 })
             }
 
@@ -433,9 +936,50 @@ impl Parser {
         })
     }
 
+    fn index(&mut self, target: Expr) -> Result<Expr, ParserError> {
+        log::debug!("index() at pos {}", self.pos);
+        self.expect(&Token::LBracket)?;
+        self.paren_depth += 1;
+        let index = self.expression(Precedence::Lowest)?;
+        self.expect(&Token::RBracket)?;
+        self.paren_depth -= 1;
+        Ok(Expr::Index {
+            target: Box::new(target),
+            index: Box::new(index),
+        })
+    }
+
+    fn list(&mut self) -> Result<Expr, ParserError> {
+        log::debug!("list() at pos {}", self.pos);
+        self.expect(&Token::LBracket)?;
+        self.paren_depth += 1;
+        let mut items = Vec::new();
+
+        self.whitespace();
+        if self.current() != Some(&Token::RBracket) {
+            loop {
+                items.push(self.expression(Precedence::Lowest)?);
+
+                self.whitespace();
+                if self.current() == Some(&Token::Comma) {
+                    self.advance();
+                } else {
+                    break;
+                }
+            }
+        }
+
+        self.expect(&Token::RBracket)?;
+        self.paren_depth -= 1;
+        Ok(Expr::List(items))
+    }
+
     fn arguments(&mut self) -> Result<Vec<Expr>, ParserError> {
         log::debug!("arguments() at pos {}", self.pos);
+        self.whitespace();
+        let (line, col, pos) = self.position();
         self.expect(&Token::LParen)?;
+        self.paren_depth += 1;
         let mut args = Vec::new();
 
         self.whitespace();
@@ -452,12 +996,97 @@ impl Parser {
             }
         }
 
-        self.expect(&Token::RParen)?;
+        // Running out of input is reported where the call was opened, not at the end
+        match self.expect(&Token::RParen) {
+            Err(ParserError::UnexpectedEof { .. }) => {
+                return Err(ParserError::UnclosedCall { line, col, pos });
+            }
+            result => result?,
+        }
+        self.paren_depth -= 1;
         Ok(args)
     }
 
+    fn is_call(&self) -> bool {
+        if self.has_whitespace_before() {
+            return false;
+        }
+
+        // `3x(x+1)` multiplies while `n * f(n-1)` calls `f`
+        let before_callee = self
+            .pos
+            .checked_sub(2)
+            .and_then(|i| self.tokens.get(i))
+            .map(|s| &s.value);
+
+        !matches!(
+            before_callee,
+            Some(Token::Number(_) | Token::Integer(_)) | Some(Token::RParen)
+        )
+    }
+
+    // Whether the token after the current one can start an operand
+    fn has_operand_after(&self) -> bool {
+        let next = self.tokens[self.pos + 1..]
+            .iter()
+            .map(|spanned| &spanned.value)
+            .find(|token| match token {
+                Token::Whitespace => false,
+                Token::Newline => self.paren_depth == 0,
+                _ => true,
+            });
+
+        matches!(
+            next,
+            Some(
+                Token::Number(_)
+                    | Token::Integer(_)
+                    | Token::String(_)
+                    | Token::Boolean(_)
+                    | Token::Identifier(_)
+                    | Token::Minus
+                    | Token::Plus
+                    | Token::Exclamation
+                    | Token::LParen
+                    | Token::LBracket
+                    | Token::LBrace
+                    | Token::Pipe
+                    | Token::Const
+                    | Token::If
+            )
+        )
+    }
+
+    // Reads a `||` at the current position as two bars
+    fn split_pipes(&mut self) {
+        let Some(spanned) = self.tokens.get(self.pos) else {
+            return;
+        };
+        if spanned.value != Token::PipePipe {
+            return;
+        }
+
+        let span = spanned.span.clone();
+        self.tokens[self.pos] = Token::Pipe.span(span.line, span.col, span.pos, 1);
+        self.tokens.insert(
+            self.pos + 1,
+            Token::Pipe.span(span.line, span.col + 1, span.pos + 1, 1),
+        );
+    }
+
+    // A number directly after another on the same line, with only spaces between
+    fn follows_number(&self) -> bool {
+        matches!(self.previous(), Some(Token::Number(_) | Token::Integer(_)))
+    }
+
     fn is_implicit_multiplication(&self, token: &Token) -> bool {
         log::debug!("is_implicit_multiplication() at pos {}", self.pos);
+        if matches!(token, Token::Number(_) | Token::Integer(_))
+            && self.adjacent_numbers == AdjacentNumbers::Multiply
+            && self.follows_number()
+        {
+            return true;
+        }
         if self.has_whitespace_before() {
             return false;
         }
@@ -466,17 +1095,26 @@ impl Parser {
             Token::Identifier(_) => {
                 matches!(
                     self.previous(),
-                    Some(Token::Number(_)) | Some(Token::RParen)
+                    Some(Token::Number(_) | Token::Integer(_)) | Some(Token::RParen)
                 )
             }
 
-            Token::Number(_) => {
+            Token::Number(_) | Token::Integer(_) => {
                 matches!(
                     self.previous(),
                     Some(Token::RParen) | Some(Token::Identifier(_))
                 )
             }
 
+            // `2|x|` opens a bar; inside bars, a `|` after an operand closes one instead
+            Token::Pipe => {
+                self.abs_depth == 0
+                    && matches!(
+                        self.previous(),
+                        Some(Token::Number(_) | Token::Integer(_)) | Some(Token::RParen)
+                    )
+            }
+
             _ => false,
         }
     }
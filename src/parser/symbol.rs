@@ -0,0 +1,133 @@
+// Interned identifier names. The AST and environments hold a Symbol, so names
+// are compared and hashed as integers and each distinct name is stored once.
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    sync::{LazyLock, Mutex},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+// Interned names are never freed, so a long-lived session that keeps seeing new
+// names grows by their length. Past this many bytes the parser rejects new names.
+pub const MAX_INTERNED_BYTES: usize = 1 << 20;
+
+pub(crate) struct Interner {
+    ids: HashMap<&'static str, u32>,
+    names: Vec<&'static str>,
+    bytes: usize,
+    limit: usize,
+}
+
+impl Interner {
+    pub(crate) fn with_limit(limit: usize) -> Self {
+        Interner {
+            ids: HashMap::new(),
+            names: Vec::new(),
+            bytes: 0,
+            limit,
+        }
+    }
+
+    // None when a new name would take the table past its limit
+    pub(crate) fn try_intern(&mut self, name: &str) -> Option<u32> {
+        match self.ids.get(name) {
+            Some(&id) => Some(id),
+            None if self.bytes + name.len() > self.limit => None,
+            None => Some(self.intern(name)),
+        }
+    }
+
+    fn intern(&mut self, name: &str) -> u32 {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let name: &'static str = Box::leak(name.into());
+        let id = self.names.len() as u32;
+        self.names.push(name);
+        self.ids.insert(name, id);
+        self.bytes += name.len();
+        id
+    }
+}
+
+// Shared by every thread, so a symbol means the same name wherever it is used
+static INTERNER: LazyLock<Mutex<Interner>> =
+    LazyLock::new(|| Mutex::new(Interner::with_limit(MAX_INTERNED_BYTES)));
+
+thread_local! {
+    // Names are only ever appended, so each thread keeps a copy of the ones it has
+    // looked up and only takes the lock for names interned since
+    static NAMES: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+}
+
+impl Symbol {
+    // For names the crate itself needs, which are not counted against the limit
+    pub fn intern(name: &str) -> Symbol {
+        Symbol(INTERNER.lock().unwrap().intern(name))
+    }
+
+    // For names from the source; None once the table is full
+    pub fn try_intern(name: &str) -> Option<Symbol> {
+        INTERNER.lock().unwrap().try_intern(name).map(Symbol)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        let index = self.0 as usize;
+        NAMES.with_borrow_mut(|names| {
+            if index >= names.len() {
+                let interner = INTERNER.lock().unwrap();
+                names.extend_from_slice(&interner.names[names.len()..]);
+            }
+            names[index]
+        })
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Symbol::intern(name)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(name: String) -> Self {
+        Symbol::intern(&name)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+// Written as the name, since ids depend on the order names were first seen
+#[cfg(feature = "serde")]
+impl serde::Serialize for Symbol {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Symbol {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Symbol::try_intern(&name).ok_or_else(|| {
+            serde::de::Error::custom(format!(
+                "more than {} bytes of distinct names",
+                MAX_INTERNED_BYTES
+            ))
+        })
+    }
+}
+
+// Shows the name rather than the id, so ASTs stay readable in test failures
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
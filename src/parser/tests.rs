@@ -1,8 +1,17 @@
 #[cfg(test)]
 mod parser_tests {
-    use crate::parser::{ast::Expr, error::ParserError, pratt::Parser};
+    use crate::parser::{
+        ast::Expr,
+        brackets::check_brackets,
+        error::ParserError,
+        pratt::{AdjacentNumbers, MAX_NESTING_DEPTH, MAX_TREE_DEPTH, Parser},
+        symbol::{Interner, Symbol},
+    };
 
-    use crate::lexer::{token::Token, tokenizer::Lexer};
+    use crate::lexer::{
+        token::Token,
+        tokenizer::{DoubleSlashMode, Lexer},
+    };
 
     fn parse(input: &str) -> Result<Expr, ParserError> {
         let mut lexer = Lexer::new(input);
@@ -21,9 +30,9 @@ mod parser_tests {
         assert_eq!(
             ast,
             Expr::Binary {
-                left: Box::new(Expr::Number(1.0)),
+                left: Box::new(Expr::Integer(1)),
                 op: Token::Plus,
-                right: Box::new(Expr::Number(2.0)),
+                right: Box::new(Expr::Integer(2)),
             }
         );
     }
@@ -34,12 +43,12 @@ mod parser_tests {
         assert_eq!(
             ast,
             Expr::Binary {
-                left: Box::new(Expr::Number(2.0)),
+                left: Box::new(Expr::Integer(2)),
                 op: Token::Caret,
                 right: Box::new(Expr::Binary {
-                    left: Box::new(Expr::Number(3.0)),
+                    left: Box::new(Expr::Integer(3)),
                     op: Token::Caret,
-                    right: Box::new(Expr::Number(2.0)),
+                    right: Box::new(Expr::Integer(2)),
                 })
             }
         );
@@ -51,12 +60,12 @@ mod parser_tests {
         assert_eq!(
             ast,
             Expr::Binary {
-                left: Box::new(Expr::Number(1.0)),
+                left: Box::new(Expr::Integer(1)),
                 op: Token::Plus,
                 right: Box::new(Expr::Binary {
-                    left: Box::new(Expr::Number(2.0)),
+                    left: Box::new(Expr::Integer(2)),
                     op: Token::Star,
-                    right: Box::new(Expr::Number(3.0)),
+                    right: Box::new(Expr::Integer(3)),
                 })
             }
         );
@@ -69,12 +78,12 @@ mod parser_tests {
             ast,
             Expr::Binary {
                 left: Box::new(Expr::Binary {
-                    left: Box::new(Expr::Number(1.0)),
+                    left: Box::new(Expr::Integer(1)),
                     op: Token::Plus,
-                    right: Box::new(Expr::Number(2.0)),
+                    right: Box::new(Expr::Integer(2)),
                 }),
                 op: Token::Star,
-                right: Box::new(Expr::Number(3.0)),
+                right: Box::new(Expr::Integer(3)),
             }
         );
     }
@@ -109,7 +118,7 @@ mod parser_tests {
         assert_eq!(
             ast,
             Expr::Binary {
-                left: Box::new(Expr::Number(2.0)),
+                left: Box::new(Expr::Integer(2)),
                 op: Token::Star,
                 right: Box::new(Expr::Identifier("x".into())),
             }
@@ -125,13 +134,13 @@ mod parser_tests {
                 left: Box::new(Expr::Binary {
                     left: Box::new(Expr::Identifier("x".into())),
                     op: Token::Plus,
-                    right: Box::new(Expr::Number(1.0)),
+                    right: Box::new(Expr::Integer(1)),
                 }),
                 op: Token::Star,
                 right: Box::new(Expr::Binary {
                     left: Box::new(Expr::Identifier("y".into())),
                     op: Token::Plus,
-                    right: Box::new(Expr::Number(2.0)),
+                    right: Box::new(Expr::Integer(2)),
                 }),
             }
         );
@@ -157,9 +166,9 @@ mod parser_tests {
             Expr::Call {
                 function: Box::new(Expr::Identifier("max".into())),
                 args: vec![
-                    Expr::Number(1.0),
+                    Expr::Integer(1),
                     Expr::Identifier("x".into()),
-                    Expr::Number(3.0),
+                    Expr::Integer(3),
                 ],
             }
         );
@@ -189,7 +198,38 @@ mod parser_tests {
             ast,
             Expr::Assignment {
                 target: "x".into(),
-                value: Box::new(Expr::Number(5.0)),
+                value: Box::new(Expr::Integer(5)),
+            }
+        );
+    }
+
+    #[test]
+    fn test_chained_assignment() {
+        let ast = parse("a = b = 5").unwrap();
+        assert_eq!(
+            ast,
+            Expr::Assignment {
+                target: "a".into(),
+                value: Box::new(Expr::Assignment {
+                    target: "b".into(),
+                    value: Box::new(Expr::Integer(5)),
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn test_function_body_assignment() {
+        let ast = parse("f(x) = g = x").unwrap();
+        assert_eq!(
+            ast,
+            Expr::Function {
+                name: "f".into(),
+                args: vec!["x".into()],
+                body: Box::new(Expr::Assignment {
+                    target: "g".into(),
+                    value: Box::new(Expr::Identifier("x".into())),
+                }),
             }
         );
     }
@@ -202,6 +242,38 @@ mod parser_tests {
             ParserError::InvalidAssignment { .. } => {}
             _ => panic!("Expected InvalidAssignment"),
         }
+        assert!(err.to_string().contains("Cannot assign to '5'"));
+        assert!(
+            err.to_string()
+                .ends_with("left-hand side must be a variable")
+        );
+
+        let err = parse("1 + 2 = x").unwrap_err();
+        assert!(!err.to_string().contains("=="));
+
+        // Only a condition suggests a comparison was meant
+        let err = parse("if 5 = x then 1 else 2").unwrap_err();
+        assert!(matches!(
+            err,
+            ParserError::InvalidAssignment {
+                in_condition: true,
+                ..
+            }
+        ));
+        assert!(err.to_string().contains("Cannot assign to '5'"));
+        assert!(err.to_string().ends_with("(did you mean '=='?)"));
+
+        let err = parse("if a then 5 = x else 0").unwrap_err();
+        assert!(!err.to_string().contains("=="));
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        let ast = parse("x += 2 * 3").unwrap();
+        assert_eq!(ast.to_string(), "x = x + (2 * 3)");
+
+        let err = parse("5 -= x").unwrap_err();
+        assert!(matches!(err, ParserError::InvalidAssignment { .. }));
     }
 
     #[test]
@@ -212,7 +284,7 @@ mod parser_tests {
             Expr::Binary {
                 left: Box::new(Expr::Identifier("x".into())),
                 op: Token::Less,
-                right: Box::new(Expr::Number(10.0)),
+                right: Box::new(Expr::Integer(10)),
             }
         );
     }
@@ -239,4 +311,915 @@ mod parser_tests {
             _ => panic!("Expected UnexpectedEof with expected ')'"),
         }
     }
+
+    #[test]
+    fn test_unclosed_call_points_at_opening_paren() {
+        let err = parse("max(1, 2").unwrap_err();
+        assert!(matches!(
+            err,
+            ParserError::UnclosedCall {
+                line: 1,
+                col: 4,
+                pos: 3
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "line 1, col 4: '(' of this call is never closed, expected ')'"
+        );
+
+        let errors = Parser::new(Lexer::new("x = 1\ny = f(\n  1,\n  2").tokenize().unwrap())
+            .parse()
+            .unwrap_err();
+        assert!(matches!(
+            errors[0],
+            ParserError::UnclosedCall {
+                line: 2,
+                col: 6,
+                ..
+            }
+        ));
+
+        // A wrong closing token is still reported where it is found
+        assert!(matches!(
+            parse("max(1, 2]").unwrap_err(),
+            ParserError::ExpectedToken { .. }
+        ));
+    }
+
+    #[test]
+    fn test_newline_inside_parentheses() {
+        let ast = parse("(1 +\n2)").unwrap();
+        assert_eq!(
+            ast,
+            Expr::Binary {
+                left: Box::new(Expr::Integer(1)),
+                op: Token::Plus,
+                right: Box::new(Expr::Integer(2)),
+            }
+        );
+
+        let ast = parse("max(1,\n 2)").unwrap();
+        assert_eq!(
+            ast,
+            Expr::Call {
+                function: Box::new(Expr::Identifier("max".into())),
+                args: vec![Expr::Integer(1), Expr::Integer(2)],
+            }
+        );
+    }
+
+    #[test]
+    fn test_newline_at_top_level_separates_statements() {
+        let tokens = Lexer::new("1\n2").tokenize().unwrap();
+        let exprs = Parser::new(tokens).parse().unwrap();
+        assert_eq!(exprs, vec![Expr::Integer(1), Expr::Integer(2)]);
+    }
+
+    #[test]
+    fn test_list_literal() {
+        let ast = parse("[1, x, 3]").unwrap();
+        assert_eq!(
+            ast,
+            Expr::List(vec![
+                Expr::Integer(1),
+                Expr::Identifier("x".into()),
+                Expr::Integer(3),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_membership() {
+        let ast = parse("3 in [1, 2]").unwrap();
+        assert_eq!(
+            ast,
+            Expr::Binary {
+                left: Box::new(Expr::Integer(3)),
+                op: Token::In,
+                right: Box::new(Expr::List(vec![Expr::Integer(1), Expr::Integer(2)])),
+            }
+        );
+
+        let ast = parse("x not in xs").unwrap();
+        assert_eq!(
+            ast,
+            Expr::Unary {
+                op: Token::Not,
+                expr: Box::new(Expr::Binary {
+                    left: Box::new(Expr::Identifier("x".into())),
+                    op: Token::In,
+                    right: Box::new(Expr::Identifier("xs".into())),
+                }),
+            }
+        );
+        // Printed with the prefix `!`, so the text parses again and prints the same
+        assert_eq!(ast.to_string(), "!(x in xs)");
+        assert_eq!(parse(&ast.to_string()).unwrap().to_string(), "!(x in xs)");
+    }
+
+    #[test]
+    fn test_call_after_operator() {
+        let ast = parse("n * f(n - 1)").unwrap();
+        assert_eq!(
+            ast,
+            Expr::Binary {
+                left: Box::new(Expr::Identifier("n".into())),
+                op: Token::Star,
+                right: Box::new(Expr::Call {
+                    function: Box::new(Expr::Identifier("f".into())),
+                    args: vec![Expr::Binary {
+                        left: Box::new(Expr::Identifier("n".into())),
+                        op: Token::Minus,
+                        right: Box::new(Expr::Integer(1)),
+                    }],
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn test_conditional() {
+        let ast = parse("x < 0 ? -x : x").unwrap();
+        assert_eq!(
+            ast,
+            Expr::If {
+                cond: Box::new(Expr::Binary {
+                    left: Box::new(Expr::Identifier("x".into())),
+                    op: Token::Less,
+                    right: Box::new(Expr::Integer(0)),
+                }),
+                then_branch: Box::new(Expr::Unary {
+                    op: Token::Minus,
+                    expr: Box::new(Expr::Identifier("x".into())),
+                }),
+                else_branch: Box::new(Expr::Identifier("x".into())),
+            }
+        );
+    }
+
+    #[test]
+    fn test_display_parenthesizes_nested_operations() {
+        assert_eq!(parse("2 + 3 * 4").unwrap().to_string(), "2 + (3 * 4)");
+        assert_eq!(parse("2 ^ 3 ^ 2").unwrap().to_string(), "2 ^ (3 ^ 2)");
+        assert_eq!(parse("-x + f(1, 2)").unwrap().to_string(), "(-x) + f(1, 2)");
+        assert_eq!(
+            parse("f(x) = 2x + 1").unwrap().to_string(),
+            "f(x) = (2 * x) + 1"
+        );
+    }
+
+    #[test]
+    fn test_constant_definition() {
+        let ast = parse("const g = 9.81").unwrap();
+        assert_eq!(
+            ast,
+            Expr::Constant {
+                target: "g".into(),
+                value: Box::new(Expr::Number(9.81)),
+            }
+        );
+
+        let err = parse("const 5").unwrap_err();
+        assert!(matches!(err, ParserError::InvalidConstant { .. }));
+    }
+
+    #[test]
+    fn test_index() {
+        let ast = parse("xs[-1] + 1").unwrap();
+        assert_eq!(
+            ast,
+            Expr::Binary {
+                left: Box::new(Expr::Index {
+                    target: Box::new(Expr::Identifier("xs".into())),
+                    index: Box::new(Expr::Unary {
+                        op: Token::Minus,
+                        expr: Box::new(Expr::Integer(1)),
+                    }),
+                }),
+                op: Token::Plus,
+                right: Box::new(Expr::Integer(1)),
+            }
+        );
+    }
+
+    #[test]
+    fn test_range_slice() {
+        let ast = parse("xs[1..n + 1]").unwrap();
+        assert_eq!(
+            ast,
+            Expr::Index {
+                target: Box::new(Expr::Identifier("xs".into())),
+                index: Box::new(Expr::Range {
+                    start: Box::new(Expr::Integer(1)),
+                    end: Box::new(Expr::Binary {
+                        left: Box::new(Expr::Identifier("n".into())),
+                        op: Token::Plus,
+                        right: Box::new(Expr::Integer(1)),
+                    }),
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn test_factorial() {
+        let ast = parse("5!").unwrap();
+        assert_eq!(
+            ast,
+            Expr::Postfix {
+                op: Token::Exclamation,
+                expr: Box::new(Expr::Integer(5)),
+            }
+        );
+    }
+
+    #[test]
+    fn test_factorial_binds_tighter_than_power_and_negation() {
+        let ast = parse("2^3!").unwrap();
+        assert_eq!(
+            ast,
+            Expr::Binary {
+                left: Box::new(Expr::Integer(2)),
+                op: Token::Caret,
+                right: Box::new(Expr::Postfix {
+                    op: Token::Exclamation,
+                    expr: Box::new(Expr::Integer(3)),
+                }),
+            }
+        );
+
+        let ast = parse("-3!").unwrap();
+        assert_eq!(
+            ast,
+            Expr::Unary {
+                op: Token::Minus,
+                expr: Box::new(Expr::Postfix {
+                    op: Token::Exclamation,
+                    expr: Box::new(Expr::Integer(3)),
+                }),
+            }
+        );
+        assert_eq!(parse("(1 + 2)!").unwrap().to_string(), "(1 + 2)!");
+    }
+
+    #[test]
+    fn test_block() {
+        let ast = parse("(1; 2)").unwrap();
+        assert_eq!(ast, Expr::Block(vec![Expr::Integer(1), Expr::Integer(2)]));
+
+        let ast = parse("(1)").unwrap();
+        assert_eq!(ast, Expr::Integer(1));
+    }
+
+    #[test]
+    fn test_brace_block() {
+        let ast = parse("{ a = 1; b = 2; a + b }").unwrap();
+        let Expr::Block(exprs) = &ast else {
+            panic!("Expected a block, found {:?}", ast);
+        };
+        assert_eq!(exprs.len(), 3);
+        assert_eq!(exprs[2].to_string(), "a + b");
+        assert_eq!(ast.to_string(), "(a = 1; b = 2; a + b)");
+
+        // Unlike parentheses, braces around one expression still make a block
+        assert_eq!(parse("{1}").unwrap(), Expr::Block(vec![Expr::Integer(1)]));
+        assert_eq!(parse("{x = 1}").unwrap().to_string(), "{x = 1}");
+        assert_eq!(parse("{}").unwrap(), Expr::Block(vec![]));
+        assert!(parse("{1; 2").is_err());
+    }
+
+    #[test]
+    fn test_multiline_brace_block() {
+        let ast = parse("f(x) = {\n    y = x * 2\n\n    y + 1\n}").unwrap();
+        assert_eq!(ast.to_string(), "f(x) = (y = x * 2; y + 1)");
+
+        // Newlines and semicolons both separate statements
+        assert_eq!(
+            parse("{\n  1;\n  2\n  3;\n}").unwrap(),
+            Expr::Block(vec![Expr::Integer(1), Expr::Integer(2), Expr::Integer(3)])
+        );
+        // Parentheses inside a block still continue over newlines
+        assert_eq!(
+            parse("{\n  (1 +\n    2)\n}").unwrap().to_string(),
+            "{1 + 2}"
+        );
+
+        let statements = Parser::new(Lexer::new("g() = {\n  1\n}\ng()").tokenize().unwrap())
+            .parse()
+            .unwrap();
+        assert_eq!(statements.len(), 2);
+    }
+
+    #[test]
+    fn test_block_function_body() {
+        let ast = parse("f(x) = (y = x * 2; y + 1)").unwrap();
+        assert_eq!(ast.to_string(), "f(x) = (y = x * 2; y + 1)");
+        let Expr::Function { body, .. } = ast else {
+            panic!("Expected a function definition, found {:?}", ast);
+        };
+        assert!(matches!(*body, Expr::Block(ref exprs) if exprs.len() == 2));
+    }
+
+    fn abs(expr: Expr) -> Expr {
+        Expr::Unary {
+            op: Token::Pipe,
+            expr: Box::new(expr),
+        }
+    }
+
+    #[test]
+    fn test_absolute_value_bars() {
+        let ident = |name: &str| Expr::Identifier(name.into());
+
+        assert_eq!(parse("|x|").unwrap(), abs(ident("x")));
+        assert_eq!(
+            parse("|a| + |b|").unwrap(),
+            Expr::Binary {
+                left: Box::new(abs(ident("a"))),
+                op: Token::Plus,
+                right: Box::new(abs(ident("b"))),
+            }
+        );
+        assert_eq!(parse("|a - b|^2").unwrap().to_string(), "|a - b| ^ 2");
+
+        // An opening bar right after a number or `)` starts an implicit product
+        let product = times(Expr::Integer(2), abs(ident("x")));
+        assert_eq!(parse("2|x|").unwrap(), product);
+        assert_eq!(parse("(2)|x|").unwrap(), product);
+        assert_eq!(parse("-2|x|").unwrap(), negate(product.clone()));
+        assert_eq!(parse("2|x|^2").unwrap().to_string(), "2 * (|x| ^ 2)");
+        assert_eq!(
+            parse("2 |x|").unwrap_err().to_string(),
+            "line 1, col 3: Unexpected token '|'"
+        );
+    }
+
+    #[test]
+    fn test_nested_absolute_value_bars() {
+        let ident = |name: &str| Expr::Identifier(name.into());
+
+        // `||` opening or closing two bars is not `or`
+        assert_eq!(
+            parse("||x| - 1|").unwrap(),
+            abs(Expr::Binary {
+                left: Box::new(abs(ident("x"))),
+                op: Token::Minus,
+                right: Box::new(Expr::Integer(1)),
+            })
+        );
+        assert_eq!(parse("|1 - |x||").unwrap().to_string(), "|1 - |x||");
+        assert_eq!(parse("| |x| |").unwrap(), abs(abs(ident("x"))));
+        assert_eq!(parse("a || b").unwrap().to_string(), "a || b");
+        assert!(parse("|x").is_err());
+    }
+
+    #[test]
+    fn test_prefix_not_and_postfix_factorial() {
+        let x = || Box::new(Expr::Identifier("x".into()));
+
+        assert_eq!(
+            parse("!x").unwrap(),
+            Expr::Unary {
+                op: Token::Exclamation,
+                expr: x(),
+            }
+        );
+        assert_eq!(
+            parse("x!").unwrap(),
+            Expr::Postfix {
+                op: Token::Exclamation,
+                expr: x(),
+            }
+        );
+        assert_eq!(
+            parse("!x!").unwrap(),
+            Expr::Unary {
+                op: Token::Exclamation,
+                expr: Box::new(Expr::Postfix {
+                    op: Token::Exclamation,
+                    expr: x(),
+                }),
+            }
+        );
+        assert_eq!(
+            parse("!5 == 0").unwrap(),
+            Expr::Binary {
+                left: Box::new(Expr::Unary {
+                    op: Token::Exclamation,
+                    expr: Box::new(Expr::Integer(5)),
+                }),
+                op: Token::EqualEqual,
+                right: Box::new(Expr::Integer(0)),
+            }
+        );
+    }
+
+    #[test]
+    fn test_prefix_not_precedence() {
+        let ident = |name: &str| Box::new(Expr::Identifier(name.into()));
+        let not = |expr: Box<Expr>| {
+            Box::new(Expr::Unary {
+                op: Token::Exclamation,
+                expr,
+            })
+        };
+        let less = |left: Box<Expr>, right: Box<Expr>| Expr::Binary {
+            left,
+            op: Token::Less,
+            right,
+        };
+
+        // `!` binds tighter than a comparison, so negating one needs parentheses
+        assert_eq!(parse("!a < b").unwrap(), less(not(ident("a")), ident("b")));
+        assert_eq!(
+            parse("!(a < b)").unwrap(),
+            *not(Box::new(less(ident("a"), ident("b"))))
+        );
+    }
+
+    #[test]
+    fn test_logical_operator_precedence() {
+        let ast = parse("a or b and c == d").unwrap();
+        assert_eq!(ast.to_string(), "a or (b and (c == d))");
+
+        let ast = parse("true and false").unwrap();
+        assert_eq!(
+            ast,
+            Expr::Binary {
+                left: Box::new(Expr::Boolean(true)),
+                op: Token::And,
+                right: Box::new(Expr::Boolean(false)),
+            }
+        );
+    }
+
+    #[test]
+    fn test_string_literal() {
+        let ast = parse(r#"greeting = "say \"hi\"\n""#).unwrap();
+        assert_eq!(
+            ast,
+            Expr::Assignment {
+                target: "greeting".into(),
+                value: Box::new(Expr::String("say \"hi\"\n".to_string())),
+            }
+        );
+        assert_eq!(ast.to_string(), r#"greeting = "say \"hi\"\n""#);
+    }
+
+    #[test]
+    fn test_default_assignment() {
+        let ast = parse("x ?= 1 + 2").unwrap();
+        assert_eq!(
+            ast,
+            Expr::DefaultAssignment {
+                target: "x".into(),
+                value: Box::new(Expr::Binary {
+                    left: Box::new(Expr::Integer(1)),
+                    op: Token::Plus,
+                    right: Box::new(Expr::Integer(2)),
+                }),
+            }
+        );
+        assert_eq!(ast.to_string(), "x ?= 1 + 2");
+
+        let err = parse("2 ?= 5").unwrap_err();
+        assert!(matches!(err, ParserError::InvalidAssignment { .. }));
+    }
+
+    #[test]
+    fn test_lambda() {
+        let ast = parse("f = x -> x + 1").unwrap();
+        assert_eq!(
+            ast,
+            Expr::Assignment {
+                target: "f".into(),
+                value: Box::new(Expr::Lambda {
+                    param: "x".into(),
+                    body: Box::new(Expr::Binary {
+                        left: Box::new(Expr::Identifier("x".into())),
+                        op: Token::Plus,
+                        right: Box::new(Expr::Integer(1)),
+                    }),
+                }),
+            }
+        );
+
+        let ast = parse("max_by(xs, x -> abs(x))").unwrap();
+        assert_eq!(ast.to_string(), "max_by(xs, x -> abs(x))");
+        assert!(matches!(
+            parse("x -> y -> x").unwrap(),
+            Expr::Lambda { body, .. } if matches!(*body, Expr::Lambda { .. })
+        ));
+
+        let err = parse("1 + x -> x").unwrap_err();
+        assert!(matches!(err, ParserError::InvalidFunctionParameter { .. }));
+    }
+
+    #[test]
+    fn test_if_expression() {
+        let ast = parse("if x > 0 then 1 else 2").unwrap();
+        assert_eq!(
+            ast,
+            Expr::If {
+                cond: Box::new(Expr::Binary {
+                    left: Box::new(Expr::Identifier("x".into())),
+                    op: Token::Greater,
+                    right: Box::new(Expr::Integer(0)),
+                }),
+                then_branch: Box::new(Expr::Integer(1)),
+                else_branch: Box::new(Expr::Integer(2)),
+            }
+        );
+
+        let ast = parse("y = if a then b else if c then d else e + 1").unwrap();
+        assert_eq!(ast.to_string(), "y = a ? b : (c ? d : (e + 1))");
+    }
+
+    #[test]
+    fn test_if_expression_errors() {
+        let err = parse("if x = 5 then 1 else 2").unwrap_err();
+        assert!(matches!(
+            err,
+            ParserError::AssignmentInCondition { col: 4, .. }
+        ));
+        assert!(err.to_string().ends_with("(did you mean '=='?)"));
+
+        let err = parse("if x then 1").unwrap_err();
+        assert!(matches!(
+            err,
+            ParserError::UnexpectedEof { ref expected, .. } if expected == "else"
+        ));
+    }
+
+    #[test]
+    fn test_ternary_is_right_associative() {
+        let ast = parse("a ? b : c ? d : e").unwrap();
+        assert_eq!(
+            ast,
+            Expr::If {
+                cond: Box::new(Expr::Identifier("a".into())),
+                then_branch: Box::new(Expr::Identifier("b".into())),
+                else_branch: Box::new(Expr::If {
+                    cond: Box::new(Expr::Identifier("c".into())),
+                    then_branch: Box::new(Expr::Identifier("d".into())),
+                    else_branch: Box::new(Expr::Identifier("e".into())),
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn test_ternary_binds_looser_than_arithmetic() {
+        assert_eq!(
+            parse("x > 0 ? x : -x").unwrap().to_string(),
+            "(x > 0) ? x : (-x)"
+        );
+        assert_eq!(
+            parse("y = a + 1 > b ? a * 2 : b - 1").unwrap().to_string(),
+            "y = ((a + 1) > b) ? (a * 2) : (b - 1)"
+        );
+    }
+
+    #[test]
+    fn test_error_span_covers_token() {
+        let err = parse("1 + else").unwrap_err();
+        assert!(matches!(err, ParserError::UnexpectedToken { .. }));
+        assert_eq!(err.span_len(), 4);
+
+        let err = parse("if x then 1 1_000").unwrap_err();
+        assert!(matches!(err, ParserError::ExpectedToken { .. }));
+        assert_eq!(err.span_len(), 5);
+    }
+
+    fn negate(expr: Expr) -> Expr {
+        Expr::Unary {
+            op: Token::Minus,
+            expr: Box::new(expr),
+        }
+    }
+
+    fn times(left: Expr, right: Expr) -> Expr {
+        Expr::Binary {
+            left: Box::new(left),
+            op: Token::Star,
+            right: Box::new(right),
+        }
+    }
+
+    #[test]
+    fn test_sign_covers_implicit_product() {
+        let two = || Expr::Integer(2);
+        let x = || Expr::Identifier("x".into());
+
+        assert_eq!(parse("-2x").unwrap(), negate(times(two(), x())));
+        assert_eq!(parse("-(2x)").unwrap(), negate(times(two(), x())));
+        // An explicit `*` keeps the usual precedence: the sign binds to the number
+        assert_eq!(parse("-2*x").unwrap(), times(negate(two()), x()));
+        assert_eq!(parse("-2x * 3").unwrap().to_string(), "(-(2 * x)) * 3");
+        assert_eq!(parse("-2x^2").unwrap().to_string(), "-(2 * (x ^ 2))");
+        assert_eq!(parse("-x").unwrap(), negate(x()));
+    }
+
+    #[test]
+    fn test_signed_exponent_binds_like_unsigned() {
+        // The exponent stops before an implicit product, with or without a sign
+        assert_eq!(parse("3^2x").unwrap().to_string(), "(3 ^ 2) * x");
+        assert_eq!(parse("3^-2x").unwrap().to_string(), "(3 ^ (-2)) * x");
+        assert_eq!(parse("3^--2x").unwrap().to_string(), "(3 ^ (-(-2))) * x");
+        assert_eq!(parse("3^-(2x)").unwrap().to_string(), "3 ^ (-(2 * x))");
+        assert_eq!(parse("1/-2x").unwrap().to_string(), "1 / (-(2 * x))");
+    }
+
+    #[test]
+    fn test_identifiers_are_interned() {
+        let Expr::Binary { left, right, .. } = parse("total + total").unwrap() else {
+            panic!("expected a binary expression");
+        };
+        assert_eq!(left, right);
+
+        // Every occurrence of a name shares one stored string
+        let symbol = Symbol::intern("total");
+        assert_eq!(*left, Expr::Identifier(symbol));
+        let owned = Symbol::from(String::from("total"));
+        assert!(std::ptr::eq(symbol.as_str(), owned.as_str()));
+        assert_ne!(symbol, Symbol::intern("totals"));
+
+        assert_eq!(symbol.to_string(), "total");
+        assert_eq!(format!("{:?}", symbol), "\"total\"");
+
+        // Symbols mean the same name on every thread
+        let from_thread = std::thread::spawn(|| Symbol::intern("subtotal"))
+            .join()
+            .unwrap();
+        assert_eq!(from_thread.as_str(), "subtotal");
+        assert_eq!(from_thread, Symbol::intern("subtotal"));
+    }
+
+    #[test]
+    fn test_interner_limit() {
+        let mut interner = Interner::with_limit(8);
+        let abc = interner.try_intern("abc").unwrap();
+        let defg = interner.try_intern("defg").unwrap();
+        assert_ne!(abc, defg);
+
+        // Known names still resolve once the table is full
+        assert_eq!(interner.try_intern("xyz"), None);
+        assert_eq!(interner.try_intern("abc"), Some(abc));
+        assert_eq!(interner.try_intern("h").map(|h| h != abc), Some(true));
+        assert_eq!(interner.try_intern("i"), None);
+    }
+
+    #[test]
+    fn test_long_unary_chain() {
+        let input = format!("{}1", "-".repeat(100));
+        let mut expr = parse(&input).unwrap();
+
+        let mut depth = 0;
+        while let Expr::Unary { op, expr: inner } = expr {
+            assert_eq!(op, Token::Minus);
+            depth += 1;
+            expr = *inner;
+        }
+        assert_eq!(depth, 100);
+        assert_eq!(expr, Expr::Integer(1));
+
+        assert_eq!(parse("- -!x").unwrap().to_string(), "-(-(!x))");
+        assert_eq!(parse("--2x").unwrap().to_string(), "-(-(2 * x))");
+    }
+
+    #[test]
+    fn test_nesting_depth_limit() {
+        // Too deep to evaluate without overflowing the stack, so rejected while parsing
+        let input = format!("{}1", "-".repeat(10_000));
+        assert!(matches!(
+            parse(&input).unwrap_err(),
+            ParserError::TooDeeplyNested {
+                limit: MAX_NESTING_DEPTH,
+                line: 1,
+                ..
+            }
+        ));
+
+        // 128 levels of parentheses take more than a test thread's stack in debug builds
+        let input = format!("{}1{}", "(".repeat(1000), ")".repeat(1000));
+        let err = std::thread::Builder::new()
+            .stack_size(64 * 1024 * 1024)
+            .spawn(move || parse(&input).unwrap_err())
+            .unwrap()
+            .join()
+            .unwrap();
+        assert!(matches!(err, ParserError::TooDeeplyNested { col: 129, .. }));
+        assert!(
+            err.to_string()
+                .ends_with("Expression is nested more than 128 levels deep")
+        );
+
+        // A flat chain does not nest, but each operator deepens the tree
+        let input = vec!["1"; 500].join(" + ");
+        assert!(parse(&input).is_ok());
+        let input = vec!["1"; 10_000].join(" + ");
+        assert!(matches!(
+            parse(&input).unwrap_err(),
+            ParserError::ExpressionTooDeep {
+                limit: MAX_TREE_DEPTH,
+                ..
+            }
+        ));
+
+        // Chains inside parentheses add up
+        let chain = " + 1".repeat(200);
+        let input = format!("(((1{}){}){})", chain, chain, chain);
+        assert!(matches!(
+            parse(&input).unwrap_err(),
+            ParserError::ExpressionTooDeep { .. }
+        ));
+
+        // The limit is per statement
+        let input = vec![vec!["1"; 100].join(" + "); 3].join("\n");
+        assert!(parse(&input).is_ok());
+    }
+
+    #[test]
+    fn test_postfix_percent() {
+        let percent = |n: i64| Expr::Postfix {
+            op: Token::Percent,
+            expr: Box::new(Expr::Integer(n)),
+        };
+
+        assert_eq!(parse("50%").unwrap(), percent(50));
+        assert_eq!(
+            parse("7 % 3").unwrap(),
+            Expr::Binary {
+                left: Box::new(Expr::Integer(7)),
+                op: Token::Percent,
+                right: Box::new(Expr::Integer(3)),
+            }
+        );
+        assert_eq!(
+            parse("50% * 200").unwrap(),
+            Expr::Binary {
+                left: Box::new(percent(50)),
+                op: Token::Star,
+                right: Box::new(Expr::Integer(200)),
+            }
+        );
+        assert_eq!(parse("(50%)").unwrap(), percent(50));
+        assert_eq!(parse("f(10%, 2)").unwrap().to_string(), "f(10%, 2)");
+        assert_eq!(parse("[5%]").unwrap().to_string(), "[5%]");
+        assert_eq!(parse("7%x").unwrap().to_string(), "7 % x");
+        assert_eq!(parse("7 % -1").unwrap().to_string(), "7 % (-1)");
+    }
+
+    #[test]
+    fn test_floor_division_precedence() {
+        let tokens = Lexer::new("1 + 7 // 2 * 3")
+            .with_double_slash(DoubleSlashMode::FloorDiv)
+            .tokenize()
+            .unwrap();
+        let exprs = Parser::new(tokens).parse().unwrap();
+        assert_eq!(
+            exprs[0],
+            Expr::Binary {
+                left: Box::new(Expr::Integer(1)),
+                op: Token::Plus,
+                right: Box::new(Expr::Binary {
+                    left: Box::new(Expr::Binary {
+                        left: Box::new(Expr::Integer(7)),
+                        op: Token::SlashSlash,
+                        right: Box::new(Expr::Integer(2)),
+                    }),
+                    op: Token::Star,
+                    right: Box::new(Expr::Integer(3)),
+                }),
+            }
+        );
+        assert_eq!(exprs[0].to_string(), "1 + ((7 // 2) * 3)");
+    }
+
+    fn parse_with_policy(
+        input: &str,
+        policy: AdjacentNumbers,
+    ) -> Result<Vec<Expr>, Vec<ParserError>> {
+        let tokens = Lexer::new(input).tokenize().unwrap();
+        Parser::new(tokens).with_adjacent_numbers(policy).parse()
+    }
+
+    #[test]
+    fn test_adjacent_numbers_separate() {
+        let exprs = parse_with_policy("2 3", AdjacentNumbers::Separate).unwrap();
+        assert_eq!(exprs, vec![Expr::Integer(2), Expr::Integer(3)]);
+        // There is no statement to end inside parentheses
+        assert!(parse_with_policy("(2 3)", AdjacentNumbers::Separate).is_err());
+    }
+
+    #[test]
+    fn test_adjacent_numbers_multiply() {
+        let exprs = parse_with_policy("2 3", AdjacentNumbers::Multiply).unwrap();
+        assert_eq!(
+            exprs,
+            vec![Expr::Binary {
+                left: Box::new(Expr::Integer(2)),
+                op: Token::Star,
+                right: Box::new(Expr::Integer(3)),
+            }]
+        );
+        let exprs = parse_with_policy("1 + 2 3 ^ 2\n4", AdjacentNumbers::Multiply).unwrap();
+        assert_eq!(exprs.len(), 2);
+        assert_eq!(exprs[0].to_string(), "1 + (2 * (3 ^ 2))");
+        assert_eq!(
+            parse_with_policy("-2 3", AdjacentNumbers::Multiply).unwrap()[0].to_string(),
+            "-(2 * 3)"
+        );
+    }
+
+    #[test]
+    fn test_adjacent_numbers_error() {
+        let errors = parse_with_policy("x = 2 3", AdjacentNumbers::Error).unwrap_err();
+        assert!(matches!(
+            errors[0],
+            ParserError::AdjacentNumbers {
+                line: 1,
+                col: 7,
+                pos: 6,
+                len: 1
+            }
+        ));
+        // Only spaces count; a newline still separates expressions
+        assert_eq!(
+            parse_with_policy("2\n3", AdjacentNumbers::Error)
+                .unwrap()
+                .len(),
+            2
+        );
+        // The policy is only about two numbers
+        assert_eq!(
+            parse_with_policy("2 x", AdjacentNumbers::Error)
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    fn brackets_of(input: &str) -> Result<(), ParserError> {
+        check_brackets(&Lexer::new(input).tokenize().unwrap())
+    }
+
+    #[test]
+    fn test_check_brackets_balanced() {
+        assert!(brackets_of("f(x) = { y = [x, (1 + 2)]; y[0] }").is_ok());
+        assert!(brackets_of("format(\"{}\", 1)").is_ok());
+        assert!(brackets_of("").is_ok());
+    }
+
+    #[test]
+    fn test_check_brackets_wrong_closer() {
+        let err = brackets_of("(]").unwrap_err();
+        assert!(matches!(
+            err,
+            ParserError::MismatchedBracket {
+                open: '(',
+                found: ']',
+                line: 1,
+                col: 2,
+                ..
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "line 1, col 2: Expected ')' to close '(' from line 1, col 1, found ']'"
+        );
+
+        assert!(matches!(
+            brackets_of("1 + 2)").unwrap_err(),
+            ParserError::UnexpectedToken {
+                found: Token::RParen,
+                col: 6,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_check_brackets_unclosed() {
+        assert!(matches!(
+            brackets_of("(1 + 2").unwrap_err(),
+            ParserError::UnclosedBracket {
+                open: '(',
+                line: 1,
+                col: 1,
+                pos: 0
+            }
+        ));
+        // The innermost unclosed bracket is reported
+        assert!(matches!(
+            brackets_of("[1,\n(2").unwrap_err(),
+            ParserError::UnclosedBracket {
+                open: '(',
+                line: 2,
+                col: 1,
+                ..
+            }
+        ));
+    }
 }
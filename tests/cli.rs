@@ -0,0 +1,137 @@
+use std::{
+    env, fs,
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+fn run(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_spemath-cli"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn runs_the_script_given_as_argument() {
+    let path = env::temp_dir().join(format!("spemath-cli-{}.spemath", std::process::id()));
+    fs::write(&path, "x = 2\nx * 3\n").unwrap();
+
+    let output = run(&[path.to_str().unwrap()], "");
+    fs::remove_file(&path).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "6\n");
+}
+
+#[test]
+fn reads_stdin_without_a_path() {
+    let output = run(&[], "1 + 2\n");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+}
+
+#[test]
+fn missing_script_fails_with_an_error() {
+    let output = run(&["does-not-exist.spemath"], "");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.starts_with("Error: could not read does-not-exist.spemath"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn deep_recursion_is_an_error_instead_of_a_crash() {
+    let output = run(
+        &[],
+        "s(n) = n == 0 ? 0 : n + s(n - 1)\ns(50)\ns(1000)\ns(5000)\n",
+    );
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1275\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(
+        stderr
+            .matches("Evaluation error: Maximum recursion depth exceeded")
+            .count(),
+        2,
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn deep_nesting_is_an_error_instead_of_a_crash() {
+    let minus = format!("{}1", "-".repeat(10_000));
+    let parens = format!("{}1{}", "(".repeat(10_000), ")".repeat(10_000));
+    let lists = format!("{}1{}", "[".repeat(10_000), "]".repeat(10_000));
+    let output = run(&[], &format!("{}\n{}\n{}\n", minus, parens, lists));
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(
+        stderr
+            .matches("Expression is nested more than 128 levels deep")
+            .count(),
+        3,
+        "{}",
+        stderr
+    );
+
+    let output = run(&[], &format!("{}1\n", "-".repeat(100)));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n");
+
+    // Flat sums are only limited by how deep their tree grows
+    let sum = |terms: usize| vec!["1"; terms].join(" + ");
+    let output = run(&[], &sum(500));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "500\n");
+    let output = run(&[], &sum(100_000));
+    assert!(output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).ends_with(
+            "Expression is more than 512 operations deep, split it into smaller steps\n"
+        )
+    );
+}
+
+#[test]
+fn degrees_flag_sets_the_angle_mode() {
+    let output = run(&["--degrees"], "anglemode()\ncos(180)\n");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "deg\n-1\n");
+
+    let output = run(&[], "anglemode()\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "rad\n");
+}
+
+#[test]
+fn check_flag_prints_warnings_to_stderr() {
+    let source = "f(x) = x + y\ny = 1\nf(2)\n";
+    let output = run(&["--check"], source);
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Warning: 'f' uses 'y', which is not defined yet\n"
+    );
+
+    // Without the flag a forward reference is fine and nothing is printed
+    let output = run(&[], source);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+}
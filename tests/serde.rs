@@ -0,0 +1,23 @@
+#![cfg(feature = "serde")]
+// Round trip through the derived Serialize and Deserialize, with JSON as the format
+
+use spemath::{
+    core::runtime::parse_to_json,
+    lexer::tokenizer::Lexer,
+    parser::{ast::Expr, pratt::Parser},
+};
+
+fn parse(source: &str) -> Vec<Expr> {
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    Parser::new(tokens).parse().unwrap()
+}
+
+#[test]
+fn syntax_tree_round_trips_through_json() {
+    let exprs = parse("f(x) = x + 1");
+
+    let json = serde_json::to_string(&exprs).unwrap();
+    assert!(json.starts_with("[{\"Function\":"), "{}", json);
+    assert_eq!(serde_json::from_str::<Vec<Expr>>(&json).unwrap(), exprs);
+    assert_eq!(parse_to_json("f(x) = x + 1").unwrap(), json);
+}